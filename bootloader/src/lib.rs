@@ -1,6 +1,7 @@
 #![no_main]
 #![no_std]
 
+#[repr(C)]
 #[derive(Clone, Copy, Debug)]
 pub enum PixelFormat {
     RGBReserved8,
@@ -9,6 +10,7 @@ pub enum PixelFormat {
     BltOnly,
 }
 
+#[repr(C)]
 pub struct FrameBufferConfig {
     height: usize,
     width: usize,
@@ -55,6 +57,41 @@ impl FrameBufferConfig {
     }
 }
 
+#[repr(C)]
+pub struct InitrdConfig {
+    buffer_addr: u64,
+    size: usize,
+}
+
+impl InitrdConfig {
+    pub const fn new(buffer_addr: u64, size: usize) -> Self {
+        Self { buffer_addr, size }
+    }
+
+    pub fn address(&self) -> u64 {
+        self.buffer_addr
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+}
+
+// The loader and the kernel are built for different targets, so everything
+// handed across is repr(C) and absent values are spelled out as zero.
+#[repr(C)]
 pub struct BootInfo {
     pub frame_config: FrameBufferConfig,
+    pub initrd: InitrdConfig,
+    pub rsdp: u64,
+}
+
+impl BootInfo {
+    pub fn initrd(&self) -> Option<&InitrdConfig> {
+        (self.initrd.size != 0).then_some(&self.initrd)
+    }
+
+    pub fn rsdp(&self) -> Option<u64> {
+        (self.rsdp != 0).then_some(self.rsdp)
+    }
 }
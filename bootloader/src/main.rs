@@ -9,7 +9,7 @@ use core::{
     ptr::{copy_nonoverlapping, slice_from_raw_parts_mut, write_bytes},
 };
 
-use bootloader::{BootInfo, FrameBufferConfig};
+use bootloader::{BootInfo, FrameBufferConfig, InitrdConfig};
 use elflib::{Elf64, PT_LOAD};
 use log::info;
 use uefi::{
@@ -23,7 +23,10 @@ use uefi::{
             fs::SimpleFileSystem,
         },
     },
-    table::boot::{AllocateType, BootServices, MemoryMap, MemoryType},
+    table::{
+        boot::{AllocateType, BootServices, MemoryMap, MemoryType},
+        cfg::{ACPI2_GUID, ACPI_GUID},
+    },
 };
 
 type EntryPoint = extern "sysv64" fn(BootInfo);
@@ -77,6 +80,15 @@ fn main(image_handle: Handle, mut system_table: SystemTable<Boot>) -> Status {
 
         let elf_file = Elf64::new(kernel_buffer as u64);
         let (kernel_first_addr, kernel_last_addr) = calculate_address(&elf_file);
+        // Reserve the kernel's range so later allocations, like the initrd,
+        // are never placed on top of it.
+        let kernel_pages = ((kernel_last_addr - kernel_first_addr + 0xfff) / 0x1000) as usize;
+        bs.allocate_pages(
+            AllocateType::Address(kernel_first_addr),
+            MemoryType::LOADER_DATA,
+            kernel_pages,
+        )
+        .expect("Cannot Reserve Kernel Memory");
         copy_load_segment(&elf_file);
 
         info!("Kernel: 0x{kernel_first_addr:08X} - 0x{kernel_last_addr:08X}");
@@ -87,6 +99,31 @@ fn main(image_handle: Handle, mut system_table: SystemTable<Boot>) -> Status {
 
         unsafe { bs.free_pool(kernel_buffer).unwrap() };
 
+        // Initrd Load
+        let initrd = load_initrd(bs, &mut root_dir);
+        match &initrd {
+            Some(initrd) => info!(
+                "Initrd: 0x{:08X}, Size: {} bytes",
+                initrd.address(),
+                initrd.size()
+            ),
+            None => info!("Initrd: not found"),
+        }
+
+        // ACPI
+        let rsdp = system_table
+            .config_table()
+            .iter()
+            .find(|entry| entry.guid == ACPI2_GUID)
+            .or_else(|| {
+                system_table
+                    .config_table()
+                    .iter()
+                    .find(|entry| entry.guid == ACPI_GUID)
+            })
+            .map(|entry| entry.address as u64);
+        info!("RSDP: {:08X?}", rsdp);
+
         // GOP
 
         let gop_handle = bs.get_handle_for_protocol::<GraphicsOutput>().unwrap();
@@ -124,6 +161,8 @@ fn main(image_handle: Handle, mut system_table: SystemTable<Boot>) -> Status {
                     gop.frame_buffer().as_mut_ptr() as u64,
                     pixel_format,
                 ),
+                initrd: initrd.unwrap_or(InitrdConfig::new(0, 0)),
+                rsdp: rsdp.unwrap_or(0),
             },
         )
     };
@@ -147,6 +186,39 @@ fn open_root_dir(bs: &BootServices) -> Directory {
     fs.open_volume().unwrap()
 }
 
+// The image stays in LOADER_DATA pages, which the kernel never hands out, so
+// it remains valid after boot services are gone.
+fn load_initrd(bs: &BootServices, root_dir: &mut Directory) -> Option<InitrdConfig> {
+    let mut initrd_file = root_dir
+        .open(
+            cstr16!("\\initrd.img"),
+            FileMode::Read,
+            FileAttribute::empty(),
+        )
+        .ok()?
+        .into_regular_file()?;
+    let mut file_info_buffer = [0u8; 0x100];
+    let file_info = initrd_file
+        .get_info::<FileInfo>(&mut file_info_buffer)
+        .ok()?;
+    let initrd_size = file_info.file_size() as usize;
+    if initrd_size == 0 {
+        return None;
+    }
+
+    let pages = (initrd_size + 0xfff) / 0x1000;
+    let initrd_addr = bs
+        .allocate_pages(AllocateType::AnyPages, MemoryType::LOADER_DATA, pages)
+        .ok()?;
+    let read = initrd_file
+        .read(unsafe { &mut *slice_from_raw_parts_mut(initrd_addr as *mut u8, initrd_size) });
+    if !matches!(read, Ok(size) if size == initrd_size) {
+        let _ = unsafe { bs.free_pages(initrd_addr, pages) };
+        return None;
+    }
+    Some(InitrdConfig::new(initrd_addr, initrd_size))
+}
+
 fn save_memory_map(file: &mut RegularFile, memory_map: &MemoryMap) {
     for (idx, entry) in memory_map.entries().enumerate() {
         let buf = format!(
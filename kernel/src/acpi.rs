@@ -0,0 +1,203 @@
+use core::{mem::size_of, slice};
+
+use crate::sync::OnceLock;
+
+#[repr(C, packed)]
+struct Rsdp {
+    signature: [u8; 8],
+    checksum: u8,
+    oem_id: [u8; 6],
+    revision: u8,
+    rsdt_address: u32,
+    length: u32,
+    xsdt_address: u64,
+    extended_checksum: u8,
+    reserved: [u8; 3],
+}
+
+#[repr(C, packed)]
+pub struct SdtHeader {
+    pub signature: [u8; 4],
+    pub length: u32,
+    pub revision: u8,
+    pub checksum: u8,
+    pub oem_id: [u8; 6],
+    pub oem_table_id: [u8; 8],
+    pub oem_revision: u32,
+    pub creator_id: u32,
+    pub creator_revision: u32,
+}
+
+#[repr(C, packed)]
+pub struct McfgEntry {
+    pub base: u64,
+    pub segment: u16,
+    pub start_bus: u8,
+    pub end_bus: u8,
+    reserved: u32,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum MadtEntry {
+    LocalApic {
+        processor_id: u8,
+        apic_id: u8,
+        enabled: bool,
+    },
+    IoApic {
+        id: u8,
+        address: u32,
+        gsi_base: u32,
+    },
+    InterruptOverride {
+        source: u8,
+        gsi: u32,
+        flags: u16,
+    },
+    Other(u8),
+}
+
+struct Acpi {
+    rsdp: &'static Rsdp,
+    mcfg: &'static [McfgEntry],
+}
+
+static ACPI: OnceLock<Acpi> = OnceLock::new();
+
+fn checksum(address: u64, length: usize) -> bool {
+    let bytes = unsafe { slice::from_raw_parts(address as *const u8, length) };
+    bytes.iter().fold(0u8, |sum, &byte| sum.wrapping_add(byte)) == 0
+}
+
+impl Rsdp {
+    fn is_valid(&self) -> bool {
+        if &self.signature != b"RSD PTR " || !checksum(self as *const _ as u64, 20) {
+            return false;
+        }
+        self.revision < 2 || checksum(self as *const _ as u64, self.length as usize)
+    }
+
+    // ACPI 2.0 and later point at the XSDT with 64-bit entries, older
+    // firmware only has the RSDT with 32-bit ones.
+    fn table_iter(&self) -> impl Iterator<Item = &'static SdtHeader> {
+        let (root, entry_size) = if self.revision >= 2 && self.xsdt_address != 0 {
+            (self.xsdt_address, 8)
+        } else {
+            (self.rsdt_address as u64, 4)
+        };
+        let header = unsafe { &*(root as *const SdtHeader) };
+        let count = if header.is_valid() {
+            (header.length as usize - size_of::<SdtHeader>()) / entry_size
+        } else {
+            0
+        };
+        let entries = root + size_of::<SdtHeader>() as u64;
+        (0..count)
+            .map(move |index| {
+                let entry = entries + (index * entry_size) as u64;
+                if entry_size == 8 {
+                    unsafe { (entry as *const u64).read_unaligned() }
+                } else {
+                    unsafe { (entry as *const u32).read_unaligned() as u64 }
+                }
+            })
+            .map(|address| unsafe { &*(address as *const SdtHeader) })
+            .filter(|header| header.is_valid())
+    }
+}
+
+impl SdtHeader {
+    fn is_valid(&self) -> bool {
+        self.length as usize >= size_of::<SdtHeader>()
+            && checksum(self as *const _ as u64, self.length as usize)
+    }
+
+    pub fn address(&self) -> u64 {
+        self as *const _ as u64
+    }
+
+    // Bytes following the common header.
+    pub fn data(&self) -> &'static [u8] {
+        unsafe {
+            slice::from_raw_parts(
+                (self.address() + size_of::<SdtHeader>() as u64) as *const u8,
+                self.length as usize - size_of::<SdtHeader>(),
+            )
+        }
+    }
+}
+
+fn parse_mcfg(rsdp: &Rsdp) -> &'static [McfgEntry] {
+    let Some(mcfg) = rsdp.table_iter().find(|table| &table.signature == b"MCFG") else {
+        return &[];
+    };
+    // Eight reserved bytes sit between the header and the allocations.
+    let data = &mcfg.data()[8.min(mcfg.data().len())..];
+    unsafe {
+        slice::from_raw_parts(
+            data.as_ptr() as *const McfgEntry,
+            data.len() / size_of::<McfgEntry>(),
+        )
+    }
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+// The MADT, signed "APIC", starts with the local APIC address and flags,
+// followed by variable length records tagged with their type and length.
+pub fn madt_entries() -> impl Iterator<Item = MadtEntry> {
+    let data = find_table(b"APIC").map_or(&[][..], |madt| madt.data());
+    let mut offset = 8.min(data.len());
+    core::iter::from_fn(move || {
+        let record = data.get(offset..)?;
+        let (&kind, &length) = (record.first()?, record.get(1)?);
+        if length < 2 || record.len() < length as usize {
+            return None;
+        }
+        offset += length as usize;
+        let record = &record[..length as usize];
+        Some(match (kind, length) {
+            (0, 8..) => MadtEntry::LocalApic {
+                processor_id: record[2],
+                apic_id: record[3],
+                enabled: read_u32(record, 4) & 0x01 != 0,
+            },
+            (1, 12..) => MadtEntry::IoApic {
+                id: record[2],
+                address: read_u32(record, 4),
+                gsi_base: read_u32(record, 8),
+            },
+            (2, 10..) => MadtEntry::InterruptOverride {
+                source: record[3],
+                gsi: read_u32(record, 4),
+                flags: u16::from_le_bytes([record[8], record[9]]),
+            },
+            _ => MadtEntry::Other(kind),
+        })
+    })
+}
+
+pub fn init_acpi(rsdp_address: u64) -> Result<(), ()> {
+    let rsdp = unsafe { &*(rsdp_address as *const Rsdp) };
+    if !rsdp.is_valid() {
+        return Err(());
+    }
+    ACPI.get_or_init(|| Acpi {
+        rsdp,
+        mcfg: parse_mcfg(rsdp),
+    });
+    Ok(())
+}
+
+pub fn find_table(signature: &[u8; 4]) -> Option<&'static SdtHeader> {
+    ACPI.get()?
+        .rsdp
+        .table_iter()
+        .find(|table| &table.signature == signature)
+}
+
+pub fn mcfg_entries() -> &'static [McfgEntry] {
+    ACPI.get().map_or(&[], |acpi| acpi.mcfg)
+}
@@ -1,101 +1,455 @@
 use core::{fmt, ptr::write_bytes};
 
 use crate::{
-    font::write_ascii,
-    graphic::{GraphicWriter, PixelColor},
-    sync::{Mutex, OnceLock},
+    font::{default_font, set_default_font, write_char, Font},
+    graphic::{GraphicWriter, PixelColor, Rect},
+    sync::Mutex,
 };
 
 use log::{Level, Log};
 
-pub static CONSOLE: OnceLock<Mutex<Console>> = OnceLock::new();
+// Constructed in place so the scrollback history lives in .bss instead of
+// being built on the boot stack and moved into a OnceLock.
+pub static CONSOLE: Mutex<Console> = Mutex::new(Console::new());
 pub static LOGGER: ConsoleLogger = ConsoleLogger;
 
 pub struct Console<'a> {
-    writer: &'a GraphicWriter,
+    writer: Option<&'a GraphicWriter>,
     bg_color: PixelColor,
     fg_color: PixelColor,
-    buffer: [[u8; Console::Columns + 1]; Console::Rows],
+    buffer: [[Cell; Console::Columns + 1]; Console::HistoryRows],
     cursor_column: u64,
     cursor_row: u64,
+    rows: usize,
+    columns: usize,
+    cell_width: usize,
+    cell_height: usize,
+    last_line: usize,
+    view_offset: usize,
+    attribute: Cell,
+    escape: EscapeState,
+    utf8: Utf8State,
+    batch: Batch,
 }
 
 pub struct ConsoleLogger;
 
+// Colors are 1-based indices into ANSI_PALETTE, 0 selects the console's own
+// foreground/background color.
+#[derive(Clone, Copy)]
+struct Cell {
+    c: u16,
+    fg: u8,
+    bg: u8,
+}
+
+#[derive(Clone, Copy)]
+struct Utf8State {
+    codepoint: u32,
+    remaining: u8,
+    // Smallest codepoint that needs this many bytes, anything below it is an
+    // overlong encoding.
+    min: u32,
+}
+
+// While a batch is open, drawing is deferred: scrolls are counted instead of
+// moving pixels and only the first touched screen row is remembered.
+#[derive(Clone, Copy)]
+struct Batch {
+    depth: usize,
+    scroll: usize,
+    dirty_row: usize,
+}
+
+#[derive(Clone, Copy)]
+enum EscapeState {
+    Normal,
+    Escape,
+    Csi { params: [u16; 4], count: usize },
+}
+
+const ANSI_PALETTE: [PixelColor; 16] = [
+    PixelColor::Black,
+    PixelColor::new(170, 0, 0),
+    PixelColor::new(0, 170, 0),
+    PixelColor::new(170, 85, 0),
+    PixelColor::new(0, 0, 170),
+    PixelColor::new(170, 0, 170),
+    PixelColor::new(0, 170, 170),
+    PixelColor::new(170, 170, 170),
+    PixelColor::new(85, 85, 85),
+    PixelColor::new(255, 85, 85),
+    PixelColor::new(85, 255, 85),
+    PixelColor::new(255, 255, 85),
+    PixelColor::new(85, 85, 255),
+    PixelColor::new(255, 85, 255),
+    PixelColor::new(85, 255, 255),
+    PixelColor::White,
+];
+
+impl Cell {
+    const Default: u8 = 0;
+    const Empty: Cell = Cell {
+        c: 0,
+        fg: Cell::Default,
+        bg: Cell::Default,
+    };
+}
+
+impl Utf8State {
+    const fn new(codepoint: u32, remaining: u8, min: u32) -> Self {
+        Self {
+            codepoint,
+            remaining,
+            min,
+        }
+    }
+}
+
+impl Batch {
+    const fn new() -> Self {
+        Self {
+            depth: 0,
+            scroll: 0,
+            dirty_row: usize::MAX,
+        }
+    }
+}
+
 impl<'a> Console<'a> {
     pub const Rows: usize = 25;
     pub const Columns: usize = 80;
-    pub const fn new(
-        writer: &'a GraphicWriter,
-        bg_color: PixelColor,
-        fg_color: PixelColor,
-    ) -> Self {
+    pub const HistoryRows: usize = 1000;
+    pub const fn new() -> Self {
         Self {
-            writer,
-            bg_color,
-            fg_color,
-            buffer: [[0u8; Console::Columns + 1]; Console::Rows],
+            writer: None,
+            bg_color: PixelColor::Black,
+            fg_color: PixelColor::White,
+            buffer: [[Cell::Empty; Console::Columns + 1]; Console::HistoryRows],
             cursor_column: 0,
             cursor_row: 0,
+            rows: Console::Rows,
+            columns: Console::Columns,
+            cell_width: 8,
+            cell_height: 16,
+            last_line: 0,
+            view_offset: 0,
+            attribute: Cell::Empty,
+            escape: EscapeState::Normal,
+            utf8: Utf8State::new(0, 0, 0),
+            batch: Batch::new(),
+        }
+    }
+
+    pub fn init(&mut self, writer: &'a GraphicWriter, bg_color: PixelColor, fg_color: PixelColor) {
+        self.writer = Some(writer);
+        self.bg_color = bg_color;
+        self.fg_color = fg_color;
+        self.layout();
+    }
+
+    // Fits the text grid to the current font, never exceeding the screen or
+    // the Rows x Columns history buffer.
+    pub fn layout(&mut self) {
+        self.clear();
+        let font = default_font();
+        self.cell_width = font.width();
+        self.cell_height = font.height();
+        if let Some(writer) = self.writer {
+            let (width, height) = writer.frame_config.resolution();
+            self.columns = (width / self.cell_width).clamp(1, Console::Columns);
+            self.rows = (height / self.cell_height).clamp(1, Console::Rows);
         }
+        self.cursor_row = self.cursor_row.min(self.rows as u64 - 1);
+        self.cursor_column = self.cursor_column.min(self.columns as u64 - 1);
+        self.view_offset = self.view_offset.min(self.max_view_offset());
+        self.redraw();
     }
 
     pub fn put_string(&mut self, s: &[u8]) {
         for &c in s {
+            // A sequence cut short by anything but a continuation byte still
+            // shows up as one replacement character.
+            if self.utf8.remaining > 0 && !(0x80..=0xbf).contains(&c) {
+                self.utf8 = Utf8State::new(0, 0, 0);
+                self.put_char(char::REPLACEMENT_CHARACTER);
+            }
+            if !matches!(self.escape, EscapeState::Normal) {
+                self.put_escape(c);
+                continue;
+            }
             match c {
-                0x20..=0x7e | b'\n' => self.put_char(c),
-                _ => self.put_char(0xfe),
+                0x1b => self.escape = EscapeState::Escape,
+                0x20..=0x7e | b'\n' => self.put_char(c as char),
+                0x80..=0xff => self.put_utf8(c),
+                _ => self.put_char(char::REPLACEMENT_CHARACTER),
             }
         }
     }
 
-    pub fn put_char(&mut self, c: u8) {
+    fn put_utf8(&mut self, c: u8) {
         match c {
-            b'\n' => self.newline(),
+            0x80..=0xbf if self.utf8.remaining > 0 => {
+                self.utf8.codepoint = self.utf8.codepoint << 6 | (c & 0x3f) as u32;
+                self.utf8.remaining -= 1;
+                if self.utf8.remaining == 0 {
+                    // from_u32 already refuses surrogates and values past
+                    // U+10FFFF, C1 controls have no glyph to show.
+                    let c = char::from_u32(self.utf8.codepoint)
+                        .filter(|_| self.utf8.codepoint >= self.utf8.min)
+                        .filter(|c| !(0x80..=0x9f).contains(&(*c as u32)))
+                        .unwrap_or(char::REPLACEMENT_CHARACTER);
+                    self.put_char(c);
+                }
+            }
+            // 0xc0 and 0xc1 could only start overlong forms.
+            0xc2..=0xdf => self.utf8 = Utf8State::new((c & 0x1f) as u32, 1, 0x80),
+            0xe0..=0xef => self.utf8 = Utf8State::new((c & 0x0f) as u32, 2, 0x800),
+            0xf0..=0xf4 => self.utf8 = Utf8State::new((c & 0x07) as u32, 3, 0x10000),
+            _ => self.put_char(char::REPLACEMENT_CHARACTER),
+        }
+    }
+
+    fn put_escape(&mut self, c: u8) {
+        self.escape = match (self.escape, c) {
+            (EscapeState::Escape, b'[') => EscapeState::Csi {
+                params: [0; 4],
+                count: 0,
+            },
+            (EscapeState::Csi { mut params, count }, b'0'..=b'9') => {
+                let count = count.max(1);
+                let index = (count - 1).min(params.len() - 1);
+                params[index] = params[index]
+                    .saturating_mul(10)
+                    .saturating_add((c - b'0') as u16);
+                EscapeState::Csi { params, count }
+            }
+            (EscapeState::Csi { params, count }, b';') => EscapeState::Csi {
+                params,
+                count: count.max(1) + 1,
+            },
+            (EscapeState::Csi { params, count }, b'm') => {
+                let count = count.clamp(1, params.len());
+                for &param in &params[..count] {
+                    self.select_graphic_rendition(param);
+                }
+                EscapeState::Normal
+            }
+            // Unsupported sequences are swallowed up to their final byte.
+            (EscapeState::Csi { params, count }, 0x20..=0x3f) => EscapeState::Csi { params, count },
+            _ => EscapeState::Normal,
+        };
+    }
+
+    fn select_graphic_rendition(&mut self, param: u16) {
+        match param {
+            0 => self.attribute = Cell::Empty,
+            1 if (1..=8).contains(&self.attribute.fg) => self.attribute.fg += 8,
+            22 if (9..=16).contains(&self.attribute.fg) => self.attribute.fg -= 8,
+            30..=37 => self.attribute.fg = (param - 29) as u8,
+            39 => self.attribute.fg = Cell::Default,
+            40..=47 => self.attribute.bg = (param - 39) as u8,
+            49 => self.attribute.bg = Cell::Default,
+            90..=97 => self.attribute.fg = (param - 89) as u8 + 8,
+            100..=107 => self.attribute.bg = (param - 99) as u8 + 8,
+            _ => {}
+        }
+    }
+
+    fn color(&self, index: u8, default: PixelColor) -> PixelColor {
+        match index {
+            Cell::Default => default,
+            _ => ANSI_PALETTE[index as usize - 1],
+        }
+    }
+
+    fn draw_cell(&self, column: usize, row: usize, cell: Cell) {
+        let Some(writer) = self.writer else { return };
+        crate::debug_check!(
+            console_held: column < self.columns && row < self.rows,
+            "cell ({}, {}) outside {}x{} console",
+            column,
+            row,
+            self.columns,
+            self.rows
+        );
+        let bg_color = self.color(cell.bg, self.bg_color);
+        for y in self.cell_height * row..self.cell_height * (row + 1) {
+            for x in self.cell_width * column..self.cell_width * (column + 1) {
+                writer.write(x, y, bg_color);
+            }
+        }
+        write_char(
+            writer,
+            (self.cell_width * column) as u64,
+            (self.cell_height * row) as u64,
+            char::from_u32(cell.c as u32).unwrap_or(char::REPLACEMENT_CHARACTER),
+            self.color(cell.fg, self.fg_color),
+        );
+    }
+
+    pub fn put_char(&mut self, c: char) {
+        match c {
+            '\n' => self.newline(),
             _ => {
-                if (self.cursor_column as usize) < (Console::Columns - 1) {
-                    write_ascii(
-                        self.writer,
-                        8 * self.cursor_column,
-                        16 * self.cursor_row,
+                if (self.cursor_column as usize) < (self.columns - 1) {
+                    // Cells only keep the basic multilingual plane.
+                    let c = u16::try_from(c as u32).unwrap_or(0xfffd);
+                    let cell = Cell {
                         c,
-                        self.fg_color,
-                    );
-                    self.buffer[self.cursor_row as usize][self.cursor_column as usize] = c;
+                        ..self.attribute
+                    };
+                    if self.batch.depth > 0 {
+                        self.batch.dirty_row = self.batch.dirty_row.min(self.cursor_row as usize);
+                    } else if self.view_offset == 0 {
+                        self.draw_cell(self.cursor_column as usize, self.cursor_row as usize, cell);
+                    }
+                    self.buffer[self.last_line % Console::HistoryRows]
+                        [self.cursor_column as usize] = cell;
                     self.cursor_column += 1
                 }
             }
         }
     }
 
+    pub fn scroll_up(&mut self, lines: usize) {
+        let offset = (self.view_offset + lines).min(self.max_view_offset());
+        if offset != self.view_offset {
+            self.view_offset = offset;
+            self.redraw();
+        }
+    }
+
+    pub fn scroll_down(&mut self, lines: usize) {
+        let offset = self.view_offset.saturating_sub(lines);
+        if offset != self.view_offset {
+            self.view_offset = offset;
+            self.redraw();
+        }
+    }
+
+    pub fn page_up(&mut self) {
+        self.scroll_up(self.rows - 1);
+    }
+
+    pub fn page_down(&mut self) {
+        self.scroll_down(self.rows - 1);
+    }
+
     fn cls(&mut self) {}
 
     fn newline(&mut self) {
         self.cursor_column = 0;
-        if (self.cursor_row as usize) < Console::Rows - 1 {
+        self.last_line += 1;
+        self.buffer[self.last_line % Console::HistoryRows] = [Cell::Empty; Console::Columns + 1];
+        if (self.cursor_row as usize) < self.rows - 1 {
             self.cursor_row += 1
+        } else if self.view_offset == 0 && self.batch.depth > 0 {
+            self.batch.scroll += 1;
+            self.batch.dirty_row = self.batch.dirty_row.min(self.rows).saturating_sub(1);
+        } else if self.view_offset == 0 {
+            self.scroll_screen(1);
         } else {
-            for y in 0..16 * Console::Rows {
-                for x in 0..8 * Console::Columns {
-                    self.writer.write(x, y, self.bg_color);
-                }
+            // Keep the scrolled-back view on the same lines while output
+            // keeps arriving at the bottom.
+            self.view_offset = (self.view_offset + 1).min(self.max_view_offset());
+        }
+    }
+
+    // Defers drawing until the matching end_batch so a burst of output costs
+    // one scroll and one repaint of the touched rows instead of a redraw per
+    // line. Batches nest.
+    pub fn begin_batch(&mut self) {
+        self.batch.depth += 1;
+    }
+
+    pub fn end_batch(&mut self) {
+        match self.batch.depth {
+            0 | 1 => self.flush(),
+            _ => self.batch.depth -= 1,
+        }
+    }
+
+    // Closes every open batch and brings the screen up to date.
+    pub fn flush(&mut self) {
+        let batch = self.batch;
+        self.batch = Batch::new();
+        if self.view_offset != 0 {
+            return;
+        }
+        if batch.scroll >= self.rows {
+            self.redraw();
+            return;
+        }
+        if batch.scroll > 0 {
+            self.scroll_screen(batch.scroll);
+        }
+        let first_line = self.last_line - self.cursor_row as usize;
+        for row in batch.dirty_row..self.rows {
+            self.clear_row(row);
+            if first_line + row <= self.last_line {
+                self.draw_line(first_line + row, row);
             }
-            for row in 0..(Console::Rows - 1) {
-                for column in 0..(Console::Columns + 1) {
-                    let c = self.buffer[row + 1][column];
-                    self.buffer[row][column] = c;
-                    write_ascii(
-                        self.writer,
-                        8 * column as u64,
-                        16 * row as u64,
-                        c,
-                        self.fg_color,
-                    );
-                }
+        }
+    }
+
+    fn scroll_screen(&self, lines: usize) {
+        let Some(writer) = self.writer else { return };
+        let (width, height) = (self.cell_width * self.columns, self.cell_height * self.rows);
+        let distance = self.cell_height * lines;
+        writer.scroll(0, -(distance as isize), Rect::new(0, 0, width, height));
+        for y in height - distance..height {
+            for x in 0..width {
+                writer.write(x, y, self.bg_color);
+            }
+        }
+    }
+
+    fn max_view_offset(&self) -> usize {
+        let first_line = self.last_line - self.cursor_row as usize;
+        first_line.min(Console::HistoryRows - self.rows)
+    }
+
+    fn clear(&self) {
+        let Some(writer) = self.writer else { return };
+        for y in 0..self.cell_height * self.rows {
+            for x in 0..self.cell_width * self.columns {
+                writer.write(x, y, self.bg_color);
+            }
+        }
+    }
+
+    fn clear_row(&self, row: usize) {
+        let Some(writer) = self.writer else { return };
+        for y in self.cell_height * row..self.cell_height * (row + 1) {
+            for x in 0..self.cell_width * self.columns {
+                writer.write(x, y, self.bg_color);
+            }
+        }
+    }
+
+    fn draw_line(&self, line: usize, row: usize) {
+        for column in 0..self.columns {
+            let cell = self.buffer[line % Console::HistoryRows][column];
+            if cell.c != 0 || cell.bg != Cell::Default {
+                self.draw_cell(column, row, cell);
             }
-            for column in 0..(Console::Columns + 1) {
-                self.buffer[Console::Rows - 1][column] = 0;
+        }
+    }
+
+    fn redraw(&mut self) {
+        self.clear();
+        // Everything is repainted from the history, so pending batch damage
+        // is covered as well.
+        self.batch.scroll = 0;
+        self.batch.dirty_row = usize::MAX;
+        let first_line = self.last_line - self.cursor_row as usize - self.view_offset;
+        for row in 0..self.rows {
+            let line = first_line + row;
+            if line > self.last_line {
+                break;
             }
+            self.draw_line(line, row);
         }
     }
 }
@@ -108,10 +462,26 @@ impl fmt::Write for Console<'_> {
 }
 
 pub fn init_console(writer: &'static GraphicWriter, bg_color: PixelColor, fg_color: PixelColor) {
-    CONSOLE.get_or_init(|| Mutex::new(Console::new(writer, bg_color, fg_color)));
+    CONSOLE.lock().init(writer, bg_color, fg_color);
     let _ = log::set_logger(&LOGGER).map(|()| log::set_max_level(log::LevelFilter::Info));
 }
 
+// Runs f with console drawing deferred, then paints the result at once.
+pub fn console_batch<F, R>(f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    CONSOLE.lock().begin_batch();
+    let result = f();
+    CONSOLE.lock().end_batch();
+    result
+}
+
+pub fn set_console_font(font: Font) {
+    set_default_font(font);
+    CONSOLE.lock().layout();
+}
+
 #[macro_export]
 macro_rules! print {
     ($($arg:tt)*) => ($crate::console::_print(format_args!($($arg)*)));
@@ -130,7 +500,13 @@ impl Log for ConsoleLogger {
 
     fn log(&self, record: &log::Record) {
         if self.enabled(record.metadata()) {
-            println!("[{:5}]: {}", record.level(), record.args());
+            let color = match record.level() {
+                Level::Error => "\x1b[91m",
+                Level::Warn => "\x1b[93m",
+                Level::Info => "\x1b[92m",
+                _ => "\x1b[39m",
+            };
+            println!("[{}{:5}\x1b[0m]: {}", color, record.level(), record.args());
         }
     }
 
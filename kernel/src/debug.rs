@@ -0,0 +1,133 @@
+use core::{
+    arch::asm,
+    fmt::{self, Write},
+};
+
+use crate::{device::serial::Serial, print};
+
+extern "C" {
+    static __text_start: u8;
+    static __text_end: u8;
+}
+
+const MAX_BACKTRACE_DEPTH: usize = 16;
+
+#[macro_export]
+macro_rules! kassert {
+    (@console_held $held:expr, $cond:expr, $($arg:tt)+) => {
+        if !$cond {
+            $crate::debug::assert_failed(
+                stringify!($cond),
+                file!(),
+                line!(),
+                format_args!($($arg)+),
+                $held,
+            )
+        }
+    };
+    ($cond:expr $(,)?) => {
+        $crate::kassert!($cond, "{}", stringify!($cond))
+    };
+    ($cond:expr, $($arg:tt)+) => {
+        $crate::kassert!(@console_held false, $cond, $($arg)+)
+    };
+}
+
+// Like kassert!, but compiled out of release builds so it can guard hot
+// paths. Checks inside the console, which run with CONSOLE held, start with
+// `console_held:` so a failure is not reported through the console.
+#[macro_export]
+macro_rules! debug_check {
+    (console_held: $cond:expr, $($arg:tt)+) => {
+        if cfg!(debug_assertions) {
+            $crate::kassert!(@console_held true, $cond, $($arg)+)
+        }
+    };
+    ($($arg:tt)+) => {
+        if cfg!(debug_assertions) {
+            $crate::kassert!($($arg)+)
+        }
+    };
+}
+
+struct ConsoleOutput;
+struct SerialOutput;
+
+impl Write for ConsoleOutput {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        print!("{}", s);
+        Ok(())
+    }
+}
+
+impl Write for SerialOutput {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        s.bytes().for_each(|data| Serial::COM1.send(data));
+        Ok(())
+    }
+}
+
+fn report(
+    out: &mut impl Write,
+    expr: &str,
+    file: &str,
+    line: u32,
+    message: fmt::Arguments,
+) -> fmt::Result {
+    writeln!(out, "\x1b[97;41m KERNEL ASSERTION FAILED \x1b[0m")?;
+    writeln!(out, "  check:   {}", expr)?;
+    writeln!(out, "  message: {}", message)?;
+    writeln!(out, "  at:      {}:{}", file, line)?;
+    writeln!(out, "  backtrace:")?;
+    let mut result = Ok(());
+    backtrace(|depth, address| {
+        result = result.and_then(|()| writeln!(out, "    #{depth:<2} 0x{address:016X}"));
+    });
+    result
+}
+
+#[cold]
+pub fn assert_failed(
+    expr: &str,
+    file: &str,
+    line: u32,
+    message: fmt::Arguments,
+    console_held: bool,
+) -> ! {
+    // Printing, or panicking into the handler that prints, would take the
+    // console lock a second time, so report on the serial port and stop here
+    // instead.
+    if console_held {
+        let _ = report(&mut SerialOutput, expr, file, line, message);
+        loop {
+            unsafe { asm!("cli; hlt", options(nomem, nostack)) };
+        }
+    }
+    let _ = report(&mut ConsoleOutput, expr, file, line, message);
+    panic!("assertion failed: {}", expr);
+}
+
+// Walks the saved frame pointer chain, which the target spec keeps on for
+// every function. Stops at the first return address outside the kernel's
+// text so firmware frames without frame pointers are never dereferenced.
+pub fn backtrace<F>(mut f: F)
+where
+    F: FnMut(usize, u64),
+{
+    let text = unsafe { (&__text_start as *const u8 as u64)..(&__text_end as *const u8 as u64) };
+    let mut rbp: u64;
+    unsafe { asm!("mov {}, rbp", out(reg) rbp, options(nomem, nostack, preserves_flags)) };
+
+    for depth in 0..MAX_BACKTRACE_DEPTH {
+        if rbp == 0 || !rbp.is_multiple_of(8) {
+            break;
+        }
+        let frame = rbp as *const u64;
+        let return_address = unsafe { *frame.add(1) };
+        if !text.contains(&return_address) {
+            break;
+        }
+        f(depth, return_address);
+        rbp = unsafe { *frame };
+    }
+}
@@ -0,0 +1,182 @@
+use core::{
+    arch::{asm, x86_64::__cpuid},
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+use crate::sync::Mutex;
+
+use super::{
+    model::{device_tree, Device, DeviceClass, DeviceState, Resource},
+    serial::Serial,
+};
+
+pub trait CharDevice: Sync {
+    fn read(&self, buf: &mut [u8]) -> Result<usize, ()>;
+    fn write(&self, buf: &[u8]) -> Result<usize, ()>;
+
+    // Device specific requests in the spirit of ioctl.
+    fn control(&self, _command: u32, _arg: u64) -> Result<u64, ()> {
+        Err(())
+    }
+}
+
+pub struct Null;
+pub struct Zero;
+pub struct Random {
+    state: Mutex<u64>,
+}
+
+struct CharDeviceTable {
+    devices: [Option<(&'static str, &'static dyn CharDevice)>; 16],
+}
+
+static CHAR_DEVICES: Mutex<CharDeviceTable> = Mutex::new(CharDeviceTable {
+    devices: [None; 16],
+});
+
+static NULL: Null = Null;
+static ZERO: Zero = Zero;
+static RANDOM: Random = Random {
+    state: Mutex::new(0),
+};
+static SERIAL0: Serial = Serial::COM1;
+
+// CPUID is only asked once: 0 is not probed yet, 1 absent, 2 present.
+static HAS_RDRAND: AtomicU8 = AtomicU8::new(0);
+
+impl CharDevice for Null {
+    fn read(&self, _buf: &mut [u8]) -> Result<usize, ()> {
+        Ok(0)
+    }
+
+    fn write(&self, buf: &[u8]) -> Result<usize, ()> {
+        Ok(buf.len())
+    }
+}
+
+impl CharDevice for Zero {
+    fn read(&self, buf: &mut [u8]) -> Result<usize, ()> {
+        buf.fill(0);
+        Ok(buf.len())
+    }
+
+    fn write(&self, buf: &[u8]) -> Result<usize, ()> {
+        Ok(buf.len())
+    }
+}
+
+impl Random {
+    fn has_rdrand() -> bool {
+        match HAS_RDRAND.load(Ordering::Relaxed) {
+            0 => {
+                let present = __cpuid(1).ecx & (1 << 30) != 0;
+                HAS_RDRAND.store(present as u8 + 1, Ordering::Relaxed);
+                present
+            }
+            state => state == 2,
+        }
+    }
+
+    fn rdrand() -> Option<u64> {
+        for _ in 0..10 {
+            let value: u64;
+            let ok: u8;
+            unsafe {
+                asm!(
+                    "rdrand {}",
+                    "setc {}",
+                    out(reg) value,
+                    out(reg_byte) ok,
+                    options(nomem, nostack)
+                )
+            };
+            if ok != 0 {
+                return Some(value);
+            }
+        }
+        None
+    }
+
+    // RDRAND is retried a few times since it may run dry, then xorshift64
+    // seeded from the TSC takes over. Not suitable for anything security
+    // related.
+    fn next(&self) -> u64 {
+        if Self::has_rdrand() {
+            if let Some(value) = Self::rdrand() {
+                return value;
+            }
+        }
+        let mut state = self.state.lock();
+        if *state == 0 {
+            *state = unsafe { core::arch::x86_64::_rdtsc() } | 1;
+        }
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        *state
+    }
+}
+
+impl CharDevice for Random {
+    fn read(&self, buf: &mut [u8]) -> Result<usize, ()> {
+        for chunk in buf.chunks_mut(8) {
+            let value = self.next().to_le_bytes();
+            chunk.copy_from_slice(&value[..chunk.len()]);
+        }
+        Ok(buf.len())
+    }
+
+    fn write(&self, buf: &[u8]) -> Result<usize, ()> {
+        Ok(buf.len())
+    }
+}
+
+pub fn register_char_device(name: &'static str, device: &'static dyn CharDevice) -> Result<(), ()> {
+    let mut table = CHAR_DEVICES.lock();
+    if table.devices.iter().flatten().any(|(n, _)| *n == name) {
+        return Err(());
+    }
+    let slot = table.devices.iter_mut().find(|d| d.is_none()).ok_or(())?;
+    *slot = Some((name, device));
+    Ok(())
+}
+
+pub fn char_device(name: &str) -> Option<&'static dyn CharDevice> {
+    CHAR_DEVICES
+        .lock()
+        .devices
+        .iter()
+        .flatten()
+        .find(|(n, _)| *n == name)
+        .map(|(_, device)| *device)
+}
+
+crate::initcall!(
+    CHAR_DEVICES_INITCALL,
+    "chardev",
+    Device,
+    [],
+    init_char_devices
+);
+
+pub fn init_char_devices() -> Result<(), ()> {
+    register_char_device("null", &NULL)?;
+    register_char_device("zero", &ZERO)?;
+    register_char_device("random", &RANDOM)?;
+
+    if SERIAL0.init(38400).is_ok() {
+        register_char_device("ttyS0", &SERIAL0)?;
+        let mut tree = device_tree().lock();
+        let id = tree.register(
+            Device::new(DeviceClass::Other, None)
+                .with_name(format_args!("ttyS0"))
+                .with_resource(Resource::Io {
+                    base: 0x3f8,
+                    size: 8,
+                })
+                .with_resource(Resource::Irq(4)),
+        )?;
+        tree.set_state(id, DeviceState::Active)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,70 @@
+use core::slice;
+
+use bootloader::InitrdConfig;
+
+use crate::sync::OnceLock;
+
+use super::model::{device_tree, Device, DeviceClass, DeviceState, Resource};
+
+// Read-only view of the image the bootloader left in memory. It is addressed
+// in fixed size blocks so a filesystem can sit on it like on any other disk.
+pub struct Initrd {
+    data: &'static [u8],
+}
+
+static INITRD: OnceLock<Initrd> = OnceLock::new();
+
+impl Initrd {
+    pub const BLOCK_SIZE: usize = 512;
+
+    pub fn data(&self) -> &'static [u8] {
+        self.data
+    }
+
+    pub fn block_count(&self) -> usize {
+        self.data.len().div_ceil(Initrd::BLOCK_SIZE)
+    }
+
+    // The last block is padded with zeros when the image is not a multiple
+    // of the block size.
+    pub fn read_block(&self, lba: usize, buf: &mut [u8]) -> Result<(), ()> {
+        if buf.len() != Initrd::BLOCK_SIZE || lba >= self.block_count() {
+            return Err(());
+        }
+        let start = lba * Initrd::BLOCK_SIZE;
+        let end = (start + Initrd::BLOCK_SIZE).min(self.data.len());
+        buf[..end - start].copy_from_slice(&self.data[start..end]);
+        buf[end - start..].fill(0);
+        Ok(())
+    }
+
+    pub fn write_block(&self, _lba: usize, _buf: &[u8]) -> Result<(), ()> {
+        Err(())
+    }
+}
+
+pub fn init_initrd(config: &InitrdConfig) -> &'static Initrd {
+    INITRD.get_or_init(|| Initrd {
+        data: unsafe { slice::from_raw_parts(config.address() as *const u8, config.size()) },
+    })
+}
+
+pub fn initrd() -> Option<&'static Initrd> {
+    INITRD.get()
+}
+
+crate::initcall!(INITRD_INITCALL, "initrd", Device, [], || {
+    let Some(initrd) = initrd() else {
+        return Ok(());
+    };
+    let mut tree = device_tree().lock();
+    let id = tree.register(
+        Device::new(DeviceClass::Block, None)
+            .with_name(format_args!("initrd"))
+            .with_resource(Resource::Mmio {
+                base: initrd.data.as_ptr() as u64,
+                size: initrd.data.len() as u64,
+            }),
+    )?;
+    tree.set_state(id, DeviceState::Active)
+});
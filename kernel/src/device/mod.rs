@@ -1,4 +1,9 @@
+pub mod chardev;
+pub mod initrd;
+pub mod model;
 pub mod pci;
+pub mod pci_ids;
+pub mod serial;
 
 use core::arch::asm;
 
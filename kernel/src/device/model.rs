@@ -0,0 +1,293 @@
+use core::fmt;
+
+use crate::{
+    console::console_batch,
+    event::{record_event, Event},
+    println,
+    sync::Mutex,
+};
+
+use super::pci::Pci;
+
+pub type DeviceId = usize;
+
+// Every PCI function plus the handful of nodes registered outside of PCI.
+const MAX_DEVICES: usize = Pci::MAX_DEVICES + 16;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DeviceClass {
+    Bus,
+    Bridge,
+    Display,
+    Block,
+    Net,
+    Input,
+    Usb,
+    Other,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DeviceState {
+    Probed,
+    Active,
+    Suspended,
+    Stopped,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Resource {
+    Mmio { base: u64, size: u64 },
+    Io { base: u16, size: u16 },
+    Irq(u8),
+}
+
+impl Resource {
+    // Interrupt lines may be shared, so only address ranges can clash.
+    pub fn overlaps(&self, other: &Resource) -> bool {
+        match (*self, *other) {
+            (Resource::Mmio { base, size }, Resource::Mmio { base: b, size: s }) => {
+                base < b + s && b < base + size
+            }
+            (Resource::Io { base, size }, Resource::Io { base: b, size: s }) => {
+                (base as u32) < b as u32 + s as u32 && (b as u32) < base as u32 + size as u32
+            }
+            _ => false,
+        }
+    }
+}
+
+pub struct DeviceOps {
+    pub suspend: fn(&Device),
+    pub resume: fn(&Device),
+    pub shutdown: fn(&Device),
+}
+
+#[derive(Clone, Copy)]
+pub struct Device {
+    name: [u8; Device::NAME_LEN],
+    name_len: usize,
+    pub class: DeviceClass,
+    pub parent: Option<DeviceId>,
+    pub state: DeviceState,
+    pub ops: Option<&'static DeviceOps>,
+    resources: [Option<Resource>; Device::MAX_RESOURCES],
+}
+
+pub struct DeviceTree {
+    devices: [Option<Device>; MAX_DEVICES],
+    num_device: usize,
+}
+
+impl Device {
+    pub const NAME_LEN: usize = 24;
+    pub const MAX_RESOURCES: usize = 8;
+
+    pub fn new(class: DeviceClass, parent: Option<DeviceId>) -> Self {
+        Self {
+            name: [0u8; Device::NAME_LEN],
+            name_len: 0,
+            class,
+            parent,
+            state: DeviceState::Probed,
+            ops: None,
+            resources: [None; Device::MAX_RESOURCES],
+        }
+    }
+
+    pub fn with_name(mut self, args: fmt::Arguments) -> Self {
+        use core::fmt::Write;
+        self.name_len = 0;
+        let _ = self.write_fmt(args);
+        self
+    }
+
+    pub fn with_resource(mut self, resource: Resource) -> Self {
+        if let Some(slot) = self.resources.iter_mut().find(|r| r.is_none()) {
+            *slot = Some(resource);
+        }
+        self
+    }
+
+    pub fn with_ops(mut self, ops: &'static DeviceOps) -> Self {
+        self.ops = Some(ops);
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        core::str::from_utf8(&self.name[..self.name_len]).unwrap_or("?")
+    }
+
+    pub fn resources(&self) -> impl Iterator<Item = &Resource> {
+        self.resources.iter().flatten()
+    }
+}
+
+impl fmt::Write for Device {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for &c in s.as_bytes() {
+            if self.name_len == Device::NAME_LEN {
+                break;
+            }
+            self.name[self.name_len] = c;
+            self.name_len += 1;
+        }
+        Ok(())
+    }
+}
+
+impl DeviceTree {
+    pub const fn new() -> Self {
+        Self {
+            devices: [None; MAX_DEVICES],
+            num_device: 0,
+        }
+    }
+
+    pub fn register(&mut self, device: Device) -> Result<DeviceId, ()> {
+        if self.num_device == self.devices.len() {
+            return Err(());
+        }
+        if let Some(parent) = device.parent {
+            if parent >= self.num_device {
+                return Err(());
+            }
+        }
+
+        let id = self.num_device;
+        self.devices[id] = Some(device);
+        self.num_device += 1;
+        record_event(Event::DeviceAdded(id));
+        Ok(id)
+    }
+
+    pub fn set_state(&mut self, id: DeviceId, state: DeviceState) -> Result<(), ()> {
+        let dev = self.get_mut(id).ok_or(())?;
+        if dev.state != state {
+            dev.state = state;
+            record_event(Event::DeviceStateChanged(id, state));
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, id: DeviceId) -> Option<&Device> {
+        self.devices.get(id)?.as_ref()
+    }
+
+    pub fn get_mut(&mut self, id: DeviceId) -> Option<&mut Device> {
+        self.devices.get_mut(id)?.as_mut()
+    }
+
+    pub fn device_iter(&self) -> impl Iterator<Item = (DeviceId, &Device)> {
+        self.devices[..self.num_device]
+            .iter()
+            .enumerate()
+            .filter_map(|(id, dev)| dev.as_ref().map(|dev| (id, dev)))
+    }
+
+    pub fn children(&self, parent: DeviceId) -> impl Iterator<Item = (DeviceId, &Device)> {
+        self.device_iter()
+            .filter(move |(_, dev)| dev.parent == Some(parent))
+    }
+
+    pub fn find(&self, name: &str) -> Option<DeviceId> {
+        self.device_iter()
+            .find(|(_, dev)| dev.name() == name)
+            .map(|(id, _)| id)
+    }
+
+    // A parent is always registered before its children, so walking the
+    // table backwards visits every child before the bus it hangs off.
+    pub fn suspend(&mut self) {
+        for id in (0..self.num_device).rev() {
+            if let Some(dev) = self.devices[id].as_mut() {
+                if dev.state == DeviceState::Active {
+                    if let Some(ops) = dev.ops {
+                        (ops.suspend)(dev);
+                    }
+                    dev.state = DeviceState::Suspended;
+                    record_event(Event::DeviceStateChanged(id, dev.state));
+                }
+            }
+        }
+    }
+
+    pub fn resume(&mut self) {
+        for id in 0..self.num_device {
+            if let Some(dev) = self.devices[id].as_mut() {
+                if dev.state == DeviceState::Suspended {
+                    if let Some(ops) = dev.ops {
+                        (ops.resume)(dev);
+                    }
+                    dev.state = DeviceState::Active;
+                    record_event(Event::DeviceStateChanged(id, dev.state));
+                }
+            }
+        }
+    }
+
+    pub fn shutdown(&mut self) {
+        for id in (0..self.num_device).rev() {
+            if let Some(dev) = self.devices[id].as_mut() {
+                if dev.state != DeviceState::Stopped {
+                    if let Some(ops) = dev.ops {
+                        (ops.shutdown)(dev);
+                    }
+                    dev.state = DeviceState::Stopped;
+                    record_event(Event::DeviceStateChanged(id, dev.state));
+                }
+            }
+        }
+    }
+
+    pub fn print(&self) {
+        for (id, dev) in self.device_iter() {
+            if dev.parent.is_none() {
+                self.print_node(id, 0);
+            }
+        }
+    }
+
+    fn print_node(&self, id: DeviceId, depth: usize) {
+        let Some(dev) = self.get(id) else { return };
+        println!(
+            "{:indent$}{} [{:?}, {:?}]",
+            "",
+            dev.name(),
+            dev.class,
+            dev.state,
+            indent = depth * 2
+        );
+        for resource in dev.resources() {
+            match resource {
+                Resource::Mmio { base, size } => println!(
+                    "{:indent$}  mem 0x{base:016X} (0x{size:X})",
+                    "",
+                    indent = depth * 2
+                ),
+                Resource::Io { base, size } => println!(
+                    "{:indent$}  io  0x{base:04X} (0x{size:X})",
+                    "",
+                    indent = depth * 2
+                ),
+                Resource::Irq(irq) => println!("{:indent$}  irq {irq}", "", indent = depth * 2),
+            }
+        }
+        for (child, _) in self.children(id) {
+            self.print_node(child, depth + 1);
+        }
+    }
+}
+
+static DEVICE_TREE: Mutex<DeviceTree> = Mutex::new(DeviceTree::new());
+
+pub fn device_tree() -> &'static Mutex<DeviceTree> {
+    &DEVICE_TREE
+}
+
+pub fn register_device(device: Device) -> Result<DeviceId, ()> {
+    device_tree().lock().register(device)
+}
+
+pub fn lsdev() {
+    console_batch(|| device_tree().lock().print());
+}
@@ -1,22 +1,52 @@
-use crate::{
-    println,
-    sync::{Mutex, OnceLock},
+use core::{
+    ptr::{read_volatile, write_volatile},
+    sync::atomic::{AtomicBool, Ordering},
 };
 
-use super::Port;
+use log::warn;
 
+use crate::{acpi::mcfg_entries, console::console_batch, println, sync::Mutex};
+
+use super::{
+    model::{device_tree, Device, DeviceClass, DeviceId, DeviceState, DeviceTree, Resource},
+    pci_ids::{class_name, vendor_name},
+    Port,
+};
+
+// Sized statically since the kernel has no heap to grow a Vec on. The table
+// is large enough for a full enumeration of a real machine, so it only lives
+// in a static and is never built on the stack.
 pub struct Pci {
-    devices: [PciDevice; 32],
+    devices: [PciDevice; Pci::MAX_DEVICES],
     num_device: u64,
 }
 
+// Identification is read once while scanning, the rest of the configuration
+// space is read on demand.
 #[derive(Clone, Copy)]
 pub struct PciDevice {
     pub bus: u8,
     pub dev: u8,
     pub func: u8,
+    pub vendor_id: u16,
+    pub device_id: u16,
     pub header_type: u8,
     pub class_code: PciClass,
+    pub bars: [Option<Bar>; PciDevice::MAX_BARS],
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Bar {
+    Memory {
+        base: u64,
+        size: u64,
+        is_64bit: bool,
+        prefetchable: bool,
+    },
+    Io {
+        base: u32,
+        size: u32,
+    },
 }
 
 #[derive(Clone, Copy)]
@@ -30,10 +60,17 @@ pub struct PciClass {
 impl Pci {
     const CONFIG_ADDRESS: Port = Port::new(0x0cf8);
     const CONFIG_DATA: Port = Port::new(0x0cfc);
-
-    pub fn new() -> Self {
+    pub const MAX_DEVICES: usize = 256;
+
+    pub const fn new() -> Self {
+        let empty = PciClass {
+            base: 0,
+            sub: 0,
+            interface: 0,
+            revision_id: 0,
+        };
         Self {
-            devices: [PciDevice::new(0, 0, 0, 0, 0.into()); 32],
+            devices: [PciDevice::new(0, 0, 0, 0, 0, 0, empty); Pci::MAX_DEVICES],
             num_device: 0,
         }
     }
@@ -50,38 +87,79 @@ impl Pci {
         Self::CONFIG_DATA.in32()
     }
 
-    pub fn read_config(dev: &PciDevice, addr: u8) -> u32 {
-        Self::write_address(make_address(dev.bus, dev.dev, dev.func, addr));
+    // Memory mapped configuration space of segment 0 as described by the
+    // ACPI MCFG table. Only this reaches the extended space past 0xff. The
+    // MCFG base is where bus 0 would be, even when start_bus is not 0.
+    fn ecam_address(bus: u8, device: u8, function: u8, addr: u16) -> Option<*mut u32> {
+        let entry = mcfg_entries()
+            .iter()
+            .find(|entry| entry.segment == 0 && (entry.start_bus..=entry.end_bus).contains(&bus))?;
+        let offset = (bus as u64) << 20
+            | (device as u64) << 15
+            | (function as u64) << 12
+            | (addr & 0xffc) as u64;
+        Some((entry.base + offset) as *mut u32)
+    }
+
+    pub fn read_config_at(bus: u8, device: u8, function: u8, addr: u16) -> u32 {
+        if let Some(address) = Self::ecam_address(bus, device, function, addr) {
+            return unsafe { read_volatile(address) };
+        }
+        if addr > 0xff {
+            return 0xffff_ffff;
+        }
+        Self::write_address(make_address(bus, device, function, addr as u8));
         Self::read_data()
     }
 
-    pub fn write_config(dev: &PciDevice, addr: u8, data: u32) {
-        Self::write_address(make_address(dev.bus, dev.dev, dev.func, addr));
-        Self::write_data(data);
+    pub fn write_config_at(bus: u8, device: u8, function: u8, addr: u16, data: u32) {
+        if let Some(address) = Self::ecam_address(bus, device, function, addr) {
+            unsafe { write_volatile(address, data) };
+        } else if addr <= 0xff {
+            Self::write_address(make_address(bus, device, function, addr as u8));
+            Self::write_data(data);
+        }
+    }
+
+    pub fn read_config(dev: &PciDevice, addr: u16) -> u32 {
+        Self::read_config_at(dev.bus, dev.dev, dev.func, addr)
+    }
+
+    pub fn write_config(dev: &PciDevice, addr: u16, data: u32) {
+        Self::write_config_at(dev.bus, dev.dev, dev.func, addr, data)
     }
 
     pub fn read_vendor_id(bus: u8, device: u8, function: u8) -> u16 {
-        Self::write_address(make_address(bus, device, function, 0x00));
-        Self::read_data() as u16
+        Self::read_config_at(bus, device, function, 0x00) as u16
     }
 
     pub fn read_header_type(bus: u8, device: u8, function: u8) -> u8 {
-        Self::write_address(make_address(bus, device, function, 0x0C));
-        (Self::read_data() >> 8) as u8
+        (Self::read_config_at(bus, device, function, 0x0C) >> 16) as u8
     }
 
     pub fn read_class_code(bus: u8, device: u8, function: u8) -> PciClass {
-        Self::write_address(make_address(bus, device, function, 0x08));
-        Self::read_data().into()
+        Self::read_config_at(bus, device, function, 0x08).into()
     }
 
     pub fn read_bus_numbers(bus: u8, device: u8, function: u8) -> u32 {
-        Self::write_address(make_address(bus, device, function, 0x18));
-        Self::read_data()
+        Self::read_config_at(bus, device, function, 0x18)
     }
 
     pub fn init(&mut self) -> Result<(), ()> {
         self.num_device = 0;
+        self.scan_all()
+    }
+
+    // Walks every bus again and appends functions that were not there
+    // before, returning how many were found. Known functions keep their
+    // position so indices handed out earlier stay valid.
+    pub fn rescan(&mut self) -> Result<usize, ()> {
+        let known = self.num_device;
+        self.scan_all()?;
+        Ok((self.num_device - known) as usize)
+    }
+
+    fn scan_all(&mut self) -> Result<(), ()> {
         let header_type = Self::read_header_type(0, 0, 0);
         if header_type & 0x80 == 0 {
             return self.scan_bus(0);
@@ -116,7 +194,6 @@ impl Pci {
 
         for func in 1..8 {
             if Self::read_vendor_id(bus, device, func) == 0xffff {
-                println!("{bus}.{device}.{func}");
                 continue;
             }
             self.scan_function(bus, device, func)?
@@ -125,16 +202,23 @@ impl Pci {
     }
 
     pub fn scan_function(&mut self, bus: u8, device: u8, function: u8) -> Result<(), ()> {
+        let id = Self::read_config_at(bus, device, function, 0x00);
         let header_type = Self::read_header_type(bus, device, function);
         let class_code = Self::read_class_code(bus, device, function);
 
-        self.add_device(PciDevice::new(
+        let mut pci_device = PciDevice::new(
             bus,
             device,
             function,
+            id as u16,
+            (id >> 16) as u16,
             header_type,
             class_code,
-        ))?;
+        );
+        if self.find(bus, device, function).is_none() {
+            pci_device.bars = pci_device.probe_bars();
+        }
+        self.add_device(pci_device)?;
 
         if class_code.base == 0x06 && class_code.sub == 0x04 {
             let bus_numbers = Self::read_bus_numbers(bus, device, function);
@@ -145,10 +229,20 @@ impl Pci {
     }
 
     pub fn add_device(&mut self, device: PciDevice) -> Result<(), ()> {
+        if self.find(device.bus, device.dev, device.func).is_some() {
+            return Ok(());
+        }
         if self.num_device == self.devices.len() as u64 {
             return Err(());
         }
 
+        crate::debug_check!(
+            device.read_vendor_id() != 0xffff,
+            "adding absent function {}.{}.{}",
+            device.bus,
+            device.dev,
+            device.func
+        );
         self.devices[self.num_device as usize] = device;
         self.num_device += 1;
         Ok(())
@@ -157,16 +251,108 @@ impl Pci {
     pub fn device_iter(&self) -> &[PciDevice] {
         &self.devices[0..(self.num_device as usize)]
     }
+
+    pub fn find(&self, bus: u8, dev: u8, func: u8) -> Option<&PciDevice> {
+        self.device_iter()
+            .iter()
+            .find(|d| d.bus == bus && d.dev == dev && d.func == func)
+    }
+
+    pub fn register_devices(&self, tree: &mut DeviceTree) -> Result<DeviceId, ()> {
+        let mut bus = Device::new(DeviceClass::Bus, None)
+            .with_name(format_args!("pci0"))
+            .with_resource(Resource::Io {
+                base: 0x0cf8,
+                size: 8,
+            });
+        if let Some(entry) = mcfg_entries().iter().find(|entry| entry.segment == 0) {
+            bus = bus.with_resource(Resource::Mmio {
+                base: entry.base + ((entry.start_bus as u64) << 20),
+                size: ((entry.end_bus - entry.start_bus) as u64 + 1) << 20,
+            });
+        }
+        let root = tree.register(bus)?;
+        tree.get_mut(root).unwrap().state = DeviceState::Active;
+
+        // Devices behind a bridge are scanned right after it, so the parent
+        // of every secondary bus is known by the time its devices show up.
+        let mut bus_parent: [Option<DeviceId>; 256] = [None; 256];
+        bus_parent[0] = Some(root);
+
+        for dev in self.device_iter() {
+            let parent = bus_parent[dev.bus as usize].unwrap_or(root);
+            let mut device = Device::new(dev.class_code.device_class(), Some(parent)).with_name(
+                format_args!("pci {:02x}:{:02x}.{}", dev.bus, dev.dev, dev.func),
+            );
+            for (index, bar) in dev.bars.iter().enumerate() {
+                // BARs the firmware did not place are left at zero and do not
+                // decode anything, so they neither conflict nor get listed.
+                let Some(bar) = bar.filter(|bar| bar.is_assigned()) else {
+                    continue;
+                };
+                let resource = bar.resource();
+                let conflict = tree
+                    .device_iter()
+                    .filter(|&(id, _)| id > root)
+                    .find(|(_, other)| other.resources().any(|r| r.overlaps(&resource)));
+                if let Some((_, other)) = conflict {
+                    warn!(
+                        "pci {:02x}:{:02x}.{}: BAR{} overlaps {}",
+                        dev.bus,
+                        dev.dev,
+                        dev.func,
+                        index,
+                        other.name()
+                    );
+                }
+                device = device.with_resource(resource);
+            }
+            if let Some((line, _)) = dev.interrupt_line() {
+                device = device.with_resource(Resource::Irq(line));
+            }
+            let Ok(id) = tree.register(device) else {
+                warn!(
+                    "pci {:02x}:{:02x}.{}: device tree is full",
+                    dev.bus, dev.dev, dev.func
+                );
+                continue;
+            };
+
+            if dev.class_code.base == 0x06 && dev.class_code.sub == 0x04 {
+                let bus_numbers = Self::read_bus_numbers(dev.bus, dev.dev, dev.func);
+                bus_parent[(bus_numbers >> 8) as u8 as usize] = Some(id);
+            }
+        }
+        Ok(root)
+    }
 }
 
 impl PciDevice {
-    pub const fn new(bus: u8, dev: u8, func: u8, header_type: u8, class_code: PciClass) -> Self {
+    pub const MAX_BARS: usize = 6;
+    pub const COMMAND_IO_SPACE: u16 = 1 << 0;
+    pub const COMMAND_MEMORY_SPACE: u16 = 1 << 1;
+    pub const COMMAND_BUS_MASTER: u16 = 1 << 2;
+    pub const CAPABILITY_MSI: u8 = 0x05;
+    pub const CAPABILITY_MSIX: u8 = 0x11;
+
+    pub const fn new(
+        bus: u8,
+        dev: u8,
+        func: u8,
+        vendor_id: u16,
+        device_id: u16,
+        header_type: u8,
+        class_code: PciClass,
+    ) -> Self {
         Self {
             bus,
             dev,
             func,
+            vendor_id,
+            device_id,
             header_type,
             class_code,
+            bars: [None; PciDevice::MAX_BARS],
         }
     }
 
@@ -174,33 +360,179 @@ impl PciDevice {
         Pci::read_vendor_id(self.bus, self.dev, self.func)
     }
 
+    pub fn vendor_name(&self) -> Option<&'static str> {
+        vendor_name(self.vendor_id)
+    }
+
+    // Legacy INTx routing as left by the firmware: the interrupt line and
+    // the pin (1 = INTA) the function uses.
+    pub fn interrupt_line(&self) -> Option<(u8, u8)> {
+        let value = Pci::read_config(self, 0x3c);
+        let (line, pin) = (value as u8, (value >> 8) as u8);
+        (pin != 0 && line != 0xff).then_some((line, pin))
+    }
+
+    pub fn find_capability(&self, id: u8) -> Option<u8> {
+        if Pci::read_config(self, 0x04) & (1 << 20) == 0 {
+            return None;
+        }
+        let mut pointer = Pci::read_config(self, 0x34) as u8 & 0xfc;
+        // Bounded in case a broken device links its list into a loop.
+        for _ in 0..48 {
+            if pointer == 0 {
+                break;
+            }
+            let header = Pci::read_config(self, pointer as u16);
+            if header as u8 == id {
+                return Some(pointer);
+            }
+            pointer = (header >> 8) as u8 & 0xfc;
+        }
+        None
+    }
+
+    // Status bits in the upper half are write-one-to-clear, so only the
+    // command half is written back.
+    fn set_command(&self, bits: u16) {
+        let command = Pci::read_config(self, 0x04) & 0xffff;
+        Pci::write_config(self, 0x04, command | bits as u32);
+    }
+
+    pub fn enable_io_space(&self) {
+        self.set_command(PciDevice::COMMAND_IO_SPACE);
+    }
+
+    pub fn enable_memory_space(&self) {
+        self.set_command(PciDevice::COMMAND_MEMORY_SPACE);
+    }
+
+    pub fn enable_bus_master(&self) {
+        self.set_command(PciDevice::COMMAND_BUS_MASTER);
+    }
+
+    // Bridges only have two BARs, the rest of their header describes the
+    // secondary bus.
+    fn bar_count(&self) -> usize {
+        match self.header_type & 0x7f {
+            0 => 6,
+            1 => 2,
+            _ => 0,
+        }
+    }
+
     pub fn read_bar(&self, offset: u8) -> u64 {
-        Pci::write_address(make_address(
-            self.bus,
-            self.dev,
-            self.func,
-            0x10 + offset * 4,
-        ));
-        let bar = Pci::read_data() as u64;
-        if bar & 0x04 == 0 {
+        let bar = Pci::read_config(self, 0x10 + offset as u16 * 4) as u64;
+        if bar & 0x07 != 0x04 {
             bar
         } else {
-            Pci::write_address(make_address(
-                self.bus,
-                self.dev,
-                self.func,
-                0x10 + (offset + 4) * 4,
-            ));
-            let upper_bar = Pci::read_data() as u64;
+            let upper_bar = Pci::read_config(self, 0x10 + (offset as u16 + 1) * 4) as u64;
             bar | upper_bar << 32
         }
     }
+
+    // Sizes a BAR by writing all ones and reading back which address bits
+    // stick. Decoding is switched off meanwhile so the device never claims
+    // the bogus address, and both registers are restored afterwards.
+    fn probe_bar(&self, index: usize) -> Option<Bar> {
+        let addr = 0x10 + index as u16 * 4;
+        let command = Pci::read_config(self, 0x04) & 0xffff;
+        let decode = (PciDevice::COMMAND_IO_SPACE | PciDevice::COMMAND_MEMORY_SPACE) as u32;
+        Pci::write_config(self, 0x04, command & !decode);
+
+        let probe = |addr: u16| {
+            let value = Pci::read_config(self, addr);
+            Pci::write_config(self, addr, 0xffff_ffff);
+            let mask = Pci::read_config(self, addr);
+            Pci::write_config(self, addr, value);
+            (value, mask)
+        };
+        let (low, low_mask) = probe(addr);
+        let bar = if low & 0x01 != 0 {
+            // I/O decoders may implement only 16 address bits.
+            let mask = (low_mask & !0x03) | 0xffff_0000;
+            let size = (!mask).wrapping_add(1);
+            (low_mask & !0x03 != 0).then_some(Bar::Io {
+                base: low & !0x03,
+                size,
+            })
+        } else {
+            let is_64bit = low & 0x07 == 0x04 && index + 1 < self.bar_count();
+            let (high, high_mask) = if is_64bit {
+                probe(addr + 4)
+            } else {
+                (0, 0xffff_ffff)
+            };
+            let base = (high as u64) << 32 | (low & !0x0f) as u64;
+            let mask = (high_mask as u64) << 32 | (low_mask & !0x0f) as u64;
+            let size = (!mask).wrapping_add(1);
+            (low_mask & !0x0f != 0).then_some(Bar::Memory {
+                base,
+                size,
+                is_64bit,
+                prefetchable: low & 0x08 != 0,
+            })
+        };
+
+        Pci::write_config(self, 0x04, command);
+        bar
+    }
+
+    pub fn probe_bars(&self) -> [Option<Bar>; PciDevice::MAX_BARS] {
+        let mut bars = [None; PciDevice::MAX_BARS];
+        let mut index = 0;
+        while index < self.bar_count() {
+            let bar = self.probe_bar(index);
+            bars[index] = bar;
+            // The upper half of a 64-bit BAR is not a BAR of its own.
+            index += match bar {
+                Some(Bar::Memory { is_64bit: true, .. }) => 2,
+                _ => 1,
+            };
+        }
+        bars
+    }
+}
+
+impl Bar {
+    pub fn is_assigned(&self) -> bool {
+        match *self {
+            Bar::Memory { base, .. } => base != 0,
+            Bar::Io { base, .. } => base != 0,
+        }
+    }
+
+    pub fn resource(&self) -> Resource {
+        match *self {
+            Bar::Memory { base, size, .. } => Resource::Mmio { base, size },
+            Bar::Io { base, size } => Resource::Io {
+                base: base as u16,
+                size: size as u16,
+            },
+        }
+    }
 }
 
 impl PciClass {
+    pub fn name(&self) -> &'static str {
+        class_name(self.base, self.sub, self.interface)
+    }
+
     pub fn is_class(&self, base: u8, sub: u8, interface: u8) -> bool {
         self.base == base && self.sub == sub && self.interface == interface
     }
+
+    pub fn device_class(&self) -> DeviceClass {
+        match (self.base, self.sub) {
+            (0x01, _) => DeviceClass::Block,
+            (0x02, _) => DeviceClass::Net,
+            (0x03, _) => DeviceClass::Display,
+            (0x06, 0x04) => DeviceClass::Bridge,
+            (0x06, _) => DeviceClass::Bus,
+            (0x09, _) => DeviceClass::Input,
+            (0x0c, 0x03) => DeviceClass::Usb,
+            _ => DeviceClass::Other,
+        }
+    }
 }
 
 impl From<u32> for PciClass {
@@ -218,12 +550,81 @@ fn make_address(bus: u8, dev: u8, func: u8, addr: u8) -> u32 {
     1 << 31 | (bus as u32) << 16 | (dev as u32) << 11 | (func as u32) << 8 | (addr & 0xfc) as u32
 }
 
-static PCI_BUS: OnceLock<Mutex<Pci>> = OnceLock::new();
+static PCI_BUS: Mutex<Pci> = Mutex::new(Pci::new());
+static PCI_SCANNED: AtomicBool = AtomicBool::new(false);
+
+crate::initcall!(PCI_INITCALL, "pci", Bus, [], || {
+    init_pci();
+    Ok(())
+});
+
+crate::initcall!(PCI_DEVICES_INITCALL, "pci-devices", Device, ["pci"], || {
+    init_pci()
+        .lock()
+        .register_devices(&mut device_tree().lock())
+        .map(|_| ())
+});
+
+pub fn lspci() {
+    console_batch(|| {
+        for dev in init_pci().lock().device_iter() {
+            println!(
+                "{:02x}:{:02x}.{} {} {} [{:04x}:{:04x}] rev {:02x}",
+                dev.bus,
+                dev.dev,
+                dev.func,
+                dev.vendor_name().unwrap_or("Unknown Vendor"),
+                dev.class_code.name(),
+                dev.vendor_id,
+                dev.device_id,
+                dev.class_code.revision_id
+            );
+            for (index, bar) in dev.bars.iter().enumerate() {
+                match bar {
+                    Some(Bar::Memory {
+                        base,
+                        size,
+                        is_64bit,
+                        prefetchable,
+                    }) => println!(
+                        "  BAR{index}: mem 0x{base:016X} (0x{size:X}){}{}",
+                        if *is_64bit { " 64-bit" } else { "" },
+                        if *prefetchable { " prefetchable" } else { "" }
+                    ),
+                    Some(Bar::Io { base, size }) => {
+                        println!("  BAR{index}: io  0x{base:04X} (0x{size:X})")
+                    }
+                    None => {}
+                }
+            }
+            match dev.interrupt_line() {
+                Some((line, pin @ 1..=4)) => {
+                    println!("  IRQ {line}, pin {}", (b'A' + pin - 1) as char)
+                }
+                Some((line, pin)) => println!("  IRQ {line}, pin {pin}"),
+                None => {}
+            }
+            let msi = dev.find_capability(PciDevice::CAPABILITY_MSI).is_some();
+            let msix = dev.find_capability(PciDevice::CAPABILITY_MSIX).is_some();
+            if msi || msix {
+                println!(
+                    "  capable of{}{}",
+                    if msi { " MSI" } else { "" },
+                    if msix { " MSI-X" } else { "" }
+                );
+            }
+        }
+    });
+}
 
+// Scans on first use. A full device table stops the scan, and whatever was
+// found up to that point is kept.
 pub fn init_pci() -> &'static Mutex<Pci> {
-    PCI_BUS.get_or_init(|| {
-        let pci = Mutex::new(Pci::new());
-        pci.lock().init().unwrap();
-        pci
-    })
+    if !PCI_SCANNED.swap(true, Ordering::AcqRel) && PCI_BUS.lock().init().is_err() {
+        warn!(
+            "PCI device table full, only the first {} functions are used",
+            Pci::MAX_DEVICES
+        );
+    }
+    &PCI_BUS
 }
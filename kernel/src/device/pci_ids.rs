@@ -0,0 +1,96 @@
+// A small subset of the PCI ID database covering what shows up on common
+// chipsets and in virtual machines.
+const VENDORS: [(u16, &str); 18] = [
+    (0x1002, "Advanced Micro Devices, Inc. [AMD/ATI]"),
+    (0x1013, "Cirrus Logic"),
+    (0x1022, "Advanced Micro Devices, Inc. [AMD]"),
+    (0x1033, "NEC Corporation"),
+    (0x106b, "Apple Inc."),
+    (0x10de, "NVIDIA Corporation"),
+    (0x10ec, "Realtek Semiconductor Co., Ltd."),
+    (0x1234, "QEMU"),
+    (0x144d, "Samsung Electronics Co Ltd"),
+    (0x14e4, "Broadcom Inc."),
+    (0x15ad, "VMware"),
+    (0x1912, "Renesas Technology Corp."),
+    (0x1af4, "Red Hat, Inc. [virtio]"),
+    (0x1b21, "ASMedia Technology Inc."),
+    (0x1b36, "Red Hat, Inc. [QEMU]"),
+    (0x1c5c, "SK hynix"),
+    (0x80ee, "InnoTek Systemberatung GmbH [VirtualBox]"),
+    (0x8086, "Intel Corporation"),
+];
+
+// Matched in order, so entries with a programming interface come before the
+// catch-all for their subclass.
+const CLASSES: [(u8, u8, Option<u8>, &str); 30] = [
+    (0x01, 0x00, None, "SCSI Controller"),
+    (0x01, 0x01, None, "IDE Controller"),
+    (0x01, 0x06, Some(0x01), "SATA Controller (AHCI)"),
+    (0x01, 0x06, None, "SATA Controller"),
+    (0x01, 0x08, Some(0x02), "NVMe Controller"),
+    (0x01, 0x08, None, "Non-Volatile Memory Controller"),
+    (0x02, 0x00, None, "Ethernet Controller"),
+    (0x02, 0x80, None, "Network Controller"),
+    (0x03, 0x00, None, "VGA Controller"),
+    (0x03, 0x80, None, "Display Controller"),
+    (0x04, 0x01, None, "Audio Device"),
+    (0x04, 0x03, None, "HD Audio Controller"),
+    (0x05, 0x00, None, "RAM Memory"),
+    (0x06, 0x00, None, "Host Bridge"),
+    (0x06, 0x01, None, "ISA Bridge"),
+    (0x06, 0x04, None, "PCI Bridge"),
+    (0x06, 0x80, None, "Bridge"),
+    (0x07, 0x00, None, "Serial Controller"),
+    (0x07, 0x80, None, "Communication Controller"),
+    (0x08, 0x80, None, "System Peripheral"),
+    (0x09, 0x00, None, "Keyboard Controller"),
+    (0x09, 0x02, None, "Mouse Controller"),
+    (0x0c, 0x03, Some(0x00), "UHCI Controller"),
+    (0x0c, 0x03, Some(0x10), "OHCI Controller"),
+    (0x0c, 0x03, Some(0x20), "EHCI Controller"),
+    (0x0c, 0x03, Some(0x30), "xHCI Controller"),
+    (0x0c, 0x03, None, "USB Controller"),
+    (0x0c, 0x05, None, "SMBus"),
+    (0x0d, 0x00, None, "Wireless Controller"),
+    (0xff, 0xff, None, "Unassigned Class"),
+];
+
+const BASE_CLASSES: [&str; 0x14] = [
+    "Unclassified Device",
+    "Mass Storage Controller",
+    "Network Controller",
+    "Display Controller",
+    "Multimedia Controller",
+    "Memory Controller",
+    "Bridge",
+    "Communication Controller",
+    "Generic System Peripheral",
+    "Input Device Controller",
+    "Docking Station",
+    "Processor",
+    "Serial Bus Controller",
+    "Wireless Controller",
+    "Intelligent Controller",
+    "Satellite Communications Controller",
+    "Encryption Controller",
+    "Signal Processing Controller",
+    "Processing Accelerator",
+    "Non-Essential Instrumentation",
+];
+
+pub fn vendor_name(vendor_id: u16) -> Option<&'static str> {
+    VENDORS
+        .iter()
+        .find(|(id, _)| *id == vendor_id)
+        .map(|(_, name)| *name)
+}
+
+pub fn class_name(base: u8, sub: u8, interface: u8) -> &'static str {
+    CLASSES
+        .iter()
+        .find(|(b, s, i, _)| *b == base && *s == sub && (i.is_none() || *i == Some(interface)))
+        .map(|(_, _, _, name)| *name)
+        .or_else(|| BASE_CLASSES.get(base as usize).copied())
+        .unwrap_or("Unknown Device")
+}
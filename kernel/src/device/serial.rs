@@ -0,0 +1,95 @@
+use super::{chardev::CharDevice, Port};
+
+pub const SERIAL_SET_BAUD: u32 = 1;
+
+const UART_CLOCK: u32 = 115200;
+
+pub struct Serial {
+    base: u16,
+}
+
+impl Serial {
+    pub const COM1: Serial = Serial::new(0x3f8);
+
+    pub const fn new(base: u16) -> Self {
+        Self { base }
+    }
+
+    const fn port(&self, offset: u16) -> Port {
+        Port::new(self.base + offset)
+    }
+
+    // 8N1 with FIFOs enabled. Returns Err if the loopback test fails, which
+    // is how a missing UART shows up.
+    pub fn init(&self, baud: u32) -> Result<(), ()> {
+        self.port(1).out8(0x00);
+        self.set_baud(baud)?;
+        self.port(2).out8(0xc7);
+        self.port(4).out8(0x1e);
+        self.port(0).out8(0xae);
+        if self.port(0).in8() != 0xae {
+            return Err(());
+        }
+        self.port(4).out8(0x0f);
+        Ok(())
+    }
+
+    pub fn set_baud(&self, baud: u32) -> Result<(), ()> {
+        if baud == 0 || !UART_CLOCK.is_multiple_of(baud) {
+            return Err(());
+        }
+        let divisor = (UART_CLOCK / baud) as u16;
+        self.port(3).out8(0x80);
+        self.port(0).out8(divisor as u8);
+        self.port(1).out8((divisor >> 8) as u8);
+        self.port(3).out8(0x03);
+        Ok(())
+    }
+
+    fn line_status(&self) -> u8 {
+        self.port(5).in8()
+    }
+
+    pub fn send(&self, data: u8) {
+        while self.line_status() & 0x20 == 0 {
+            core::hint::spin_loop();
+        }
+        self.port(0).out8(data);
+    }
+
+    pub fn try_receive(&self) -> Option<u8> {
+        if self.line_status() & 0x01 != 0 {
+            Some(self.port(0).in8())
+        } else {
+            None
+        }
+    }
+}
+
+impl CharDevice for Serial {
+    fn read(&self, buf: &mut [u8]) -> Result<usize, ()> {
+        let mut count = 0;
+        while count < buf.len() {
+            match self.try_receive() {
+                Some(data) => buf[count] = data,
+                None => break,
+            }
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    fn write(&self, buf: &[u8]) -> Result<usize, ()> {
+        for &data in buf {
+            self.send(data);
+        }
+        Ok(buf.len())
+    }
+
+    fn control(&self, command: u32, arg: u64) -> Result<u64, ()> {
+        match command {
+            SERIAL_SET_BAUD => self.set_baud(arg as u32).map(|()| 0),
+            _ => Err(()),
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use core::{arch::x86_64::_rdtsc, fmt};
+
+use crate::{
+    console::console_batch,
+    device::model::{device_tree, Device, DeviceId, DeviceState},
+    println,
+    sync::Mutex,
+};
+
+// Events meant for the user, as opposed to the free-form debug log. Devices
+// are kept by ID and resolved to their name when the log is shown.
+#[derive(Clone, Copy, Debug)]
+pub enum Event {
+    Boot,
+    DeviceAdded(DeviceId),
+    DeviceStateChanged(DeviceId, DeviceState),
+}
+
+#[derive(Clone, Copy)]
+pub struct EventRecord {
+    pub sequence: u64,
+    pub timestamp: u64,
+    pub event: Event,
+}
+
+#[derive(Clone)]
+pub struct EventLog {
+    records: [Option<EventRecord>; EventLog::Capacity],
+    next_sequence: u64,
+}
+
+static EVENT_LOG: Mutex<EventLog> = Mutex::new(EventLog::new());
+
+impl EventLog {
+    pub const Capacity: usize = 128;
+
+    const fn new() -> Self {
+        Self {
+            records: [None; EventLog::Capacity],
+            next_sequence: 0,
+        }
+    }
+
+    // The oldest record is overwritten once the log is full.
+    pub fn record(&mut self, event: Event) {
+        let sequence = self.next_sequence;
+        self.records[sequence as usize % EventLog::Capacity] = Some(EventRecord {
+            sequence,
+            timestamp: unsafe { _rdtsc() },
+            event,
+        });
+        self.next_sequence += 1;
+    }
+
+    pub fn record_iter(&self) -> impl Iterator<Item = &EventRecord> {
+        let start = self.next_sequence.saturating_sub(EventLog::Capacity as u64);
+        (start..self.next_sequence)
+            .filter_map(|sequence| self.records[sequence as usize % EventLog::Capacity].as_ref())
+    }
+
+    pub fn dropped(&self) -> u64 {
+        self.next_sequence.saturating_sub(EventLog::Capacity as u64)
+    }
+}
+
+// An event with its device looked up beforehand. The device tree records
+// events while holding its own lock, so it must never be locked while the
+// event log or the console is.
+struct EventText {
+    event: Event,
+    device: Option<Device>,
+}
+
+impl EventText {
+    fn new(event: Event) -> Self {
+        let device = match event {
+            Event::Boot => None,
+            Event::DeviceAdded(id) | Event::DeviceStateChanged(id, _) => {
+                device_tree().lock().get(id).copied()
+            }
+        };
+        Self { event, device }
+    }
+
+    fn write_device(&self, f: &mut fmt::Formatter<'_>, id: DeviceId) -> fmt::Result {
+        match &self.device {
+            Some(dev) => write!(f, "{}", dev.name()),
+            None => write!(f, "device {}", id),
+        }
+    }
+}
+
+impl fmt::Display for EventText {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.event {
+            Event::Boot => write!(f, "system started"),
+            Event::DeviceAdded(id) => {
+                self.write_device(f, id)?;
+                write!(f, " added")
+            }
+            Event::DeviceStateChanged(id, state) => {
+                self.write_device(f, id)?;
+                write!(f, " is now {:?}", state)
+            }
+        }
+    }
+}
+
+pub fn record_event(event: Event) {
+    EVENT_LOG.lock().record(event);
+}
+
+pub fn events() {
+    let log = EVENT_LOG.lock().clone();
+    console_batch(|| {
+        if log.dropped() > 0 {
+            println!("({} older events dropped)", log.dropped());
+        }
+        for record in log.record_iter() {
+            let text = EventText::new(record.event);
+            println!(
+                "#{:<4} tsc {:016X} {}",
+                record.sequence, record.timestamp, text
+            );
+        }
+    });
+}
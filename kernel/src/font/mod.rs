@@ -1,7 +1,112 @@
 mod ascii;
+pub mod psf;
 
-use crate::graphic::{GraphicWriter, PixelColor};
+use crate::{
+    graphic::{GraphicWriter, PixelColor},
+    sync::Mutex,
+};
 use ascii::ASCII_FONT;
+use psf::Psf2Font;
+
+static DEFAULT_FONT: Mutex<Font> = Mutex::new(Font::Small);
+static GLYPH_CACHE: Mutex<GlyphCache> = Mutex::new(GlyphCache::new());
+
+const MAX_CACHED_WIDTH: usize = 32;
+const MAX_CACHED_HEIGHT: usize = 32;
+
+// Direct-mapped cache of glyphs of the default font rendered at their final
+// size, one bit mask per row. Drawing a cached character only walks the set
+// bits instead of looking it up in the PSF2 unicode table and decoding or
+// scaling the font bits again. Fonts too large for a mask are not cached.
+struct GlyphCache {
+    entries: [Option<CachedGlyph>; GlyphCache::Size],
+}
+
+#[derive(Clone, Copy)]
+struct CachedGlyph {
+    c: char,
+    rows: [u32; MAX_CACHED_HEIGHT],
+}
+
+impl GlyphCache {
+    const Size: usize = 128;
+
+    const fn new() -> Self {
+        Self {
+            entries: [None; GlyphCache::Size],
+        }
+    }
+
+    fn clear(&mut self) {
+        self.entries = [None; GlyphCache::Size];
+    }
+
+    fn glyph(&mut self, font: &Font, c: char) -> Option<&CachedGlyph> {
+        if font.width() > MAX_CACHED_WIDTH || font.height() > MAX_CACHED_HEIGHT {
+            return None;
+        }
+        let slot = &mut self.entries[c as usize % GlyphCache::Size];
+        if !matches!(slot, Some(glyph) if glyph.c == c) {
+            let mut rows = [0; MAX_CACHED_HEIGHT];
+            render_char(font, c, |dx, dy| rows[dy] |= 1 << dx);
+            *slot = Some(CachedGlyph { c, rows });
+        }
+        slot.as_ref()
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum Font {
+    Small,
+    Large,
+    Psf2(Psf2Font<'static>),
+}
+
+impl Font {
+    pub fn from_psf2(data: &'static [u8]) -> Result<Self, ()> {
+        Ok(Font::Psf2(Psf2Font::parse(data)?))
+    }
+
+    pub fn width(&self) -> usize {
+        match self {
+            Font::Small => 8,
+            Font::Large => 16,
+            Font::Psf2(font) => font.width(),
+        }
+    }
+
+    pub fn height(&self) -> usize {
+        match self {
+            Font::Small => 16,
+            Font::Large => 32,
+            Font::Psf2(font) => font.height(),
+        }
+    }
+
+    fn scale(&self) -> usize {
+        match self {
+            Font::Large => 2,
+            _ => 1,
+        }
+    }
+}
+
+pub fn default_font() -> Font {
+    *DEFAULT_FONT.lock()
+}
+
+pub fn set_default_font(font: Font) {
+    *DEFAULT_FONT.lock() = font;
+    GLYPH_CACHE.lock().clear();
+}
+
+pub fn line_height() -> usize {
+    default_font().height()
+}
+
+pub fn text_width(s: &str) -> usize {
+    s.chars().count() * default_font().width()
+}
 
 pub fn get_font(c: usize) -> Option<&'static [u8]> {
     if c < 256 {
@@ -13,13 +118,85 @@ pub fn get_font(c: usize) -> Option<&'static [u8]> {
 }
 
 pub fn write_ascii(writer: &GraphicWriter, x: u64, y: u64, c: u8, color: PixelColor) {
+    render_ascii(c, 1, |dx, dy| {
+        writer.write(x as usize + dx, y as usize + dy, color)
+    });
+}
+
+fn render_ascii<F>(c: u8, scale: usize, mut f: F)
+where
+    F: FnMut(usize, usize),
+{
     if let Some(font) = get_font(c as usize) {
-        for dy in 0..16usize {
-            for dx in 0..8usize {
-                if (font[dy] << dx) & 0x80 != 0 {
-                    writer.write(x as usize + dx, y as usize + dy, color)
+        for dy in 0..16 * scale {
+            for dx in 0..8 * scale {
+                if (font[dy / scale] << (dx / scale)) & 0x80 != 0 {
+                    f(dx, dy)
                 }
             }
         }
     };
 }
+
+// Calls f with the offset of every set pixel of c. Characters a PSF2 font
+// does not cover fall back to the embedded ASCII font, and anything else to
+// a placeholder block.
+fn render_char<F>(font: &Font, c: char, f: F)
+where
+    F: FnMut(usize, usize),
+{
+    if let Font::Psf2(font) = font {
+        if let Some(glyph) = font.glyph(c).or_else(|| font.glyph('\u{fffd}')) {
+            return render_glyph(font, glyph, f);
+        }
+    }
+    match c {
+        ' '..='~' => render_ascii(c as u8, font.scale(), f),
+        _ => render_ascii(0xfe, font.scale(), f),
+    }
+}
+
+// Draws with the default font, through the glyph cache when it fits.
+pub fn write_char(writer: &GraphicWriter, x: u64, y: u64, c: char, color: PixelColor) {
+    let font = default_font();
+    let mut cache = GLYPH_CACHE.lock();
+    let Some(glyph) = cache.glyph(&font, c) else {
+        drop(cache);
+        return render_char(&font, c, |dx, dy| {
+            writer.write(x as usize + dx, y as usize + dy, color)
+        });
+    };
+    for (dy, &row) in glyph.rows.iter().enumerate() {
+        let mut row = row;
+        while row != 0 {
+            let dx = row.trailing_zeros() as usize;
+            writer.write(x as usize + dx, y as usize + dy, color);
+            row &= row - 1;
+        }
+    }
+}
+
+pub fn write_str(writer: &GraphicWriter, x: u64, y: u64, s: &str, color: PixelColor) -> u64 {
+    let width = default_font().width() as u64;
+    let mut x = x;
+    for c in s.chars() {
+        write_char(writer, x, y, c, color);
+        x += width;
+    }
+    x
+}
+
+fn render_glyph<F>(font: &Psf2Font, glyph: &[u8], mut f: F)
+where
+    F: FnMut(usize, usize),
+{
+    let bytes_per_row = font.bytes_per_row();
+    for dy in 0..font.height() {
+        let row = &glyph[dy * bytes_per_row..(dy + 1) * bytes_per_row];
+        for dx in 0..font.width() {
+            if (row[dx / 8] << (dx % 8)) & 0x80 != 0 {
+                f(dx, dy)
+            }
+        }
+    }
+}
@@ -0,0 +1,119 @@
+const PSF2_MAGIC: [u8; 4] = [0x72, 0xb5, 0x4a, 0x86];
+const PSF2_HAS_UNICODE_TABLE: u32 = 0x01;
+
+const PSF2_SEPARATOR: u8 = 0xff;
+const PSF2_START_SEQUENCE: u8 = 0xfe;
+
+#[derive(Clone, Copy)]
+pub struct Psf2Font<'a> {
+    data: &'a [u8],
+    header_size: usize,
+    flags: u32,
+    length: usize,
+    char_size: usize,
+    height: usize,
+    width: usize,
+}
+
+impl<'a> Psf2Font<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<Self, ()> {
+        if data.len() < 32 || data[0..4] != PSF2_MAGIC {
+            return Err(());
+        }
+        let field = |index: usize| {
+            let offset = 4 * index;
+            u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+        };
+
+        let font = Self {
+            data,
+            header_size: field(2) as usize,
+            flags: field(3),
+            length: field(4) as usize,
+            char_size: field(5) as usize,
+            height: field(6) as usize,
+            width: field(7) as usize,
+        };
+        if font.width == 0
+            || font.height == 0
+            || font.char_size != font.bytes_per_row() * font.height
+            || font.glyph_table_end() > data.len()
+        {
+            return Err(());
+        }
+        Ok(font)
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn bytes_per_row(&self) -> usize {
+        self.width.div_ceil(8)
+    }
+
+    pub fn glyph(&self, c: char) -> Option<&'a [u8]> {
+        self.glyph_by_index(self.glyph_index(c)?)
+    }
+
+    pub fn glyph_index(&self, c: char) -> Option<usize> {
+        if self.flags & PSF2_HAS_UNICODE_TABLE != 0 {
+            self.lookup_unicode(c)
+        } else {
+            Some(c as usize).filter(|&index| index < self.length)
+        }
+    }
+
+    pub fn glyph_by_index(&self, index: usize) -> Option<&'a [u8]> {
+        if index >= self.length {
+            return None;
+        }
+        let offset = self.header_size + index * self.char_size;
+        Some(&self.data[offset..offset + self.char_size])
+    }
+
+    fn glyph_table_end(&self) -> usize {
+        self.header_size + self.length * self.char_size
+    }
+
+    // The unicode table holds one entry per glyph: the UTF-8 encoded
+    // characters it represents, optional combining sequences introduced by
+    // 0xFE, and a 0xFF terminator. Only single characters are matched.
+    fn lookup_unicode(&self, c: char) -> Option<usize> {
+        let mut encoded = [0u8; 4];
+        let encoded = c.encode_utf8(&mut encoded).as_bytes();
+
+        let table = &self.data[self.glyph_table_end()..];
+        for (index, entry) in table.split(|&b| b == PSF2_SEPARATOR).enumerate() {
+            if index >= self.length {
+                break;
+            }
+            let singles = entry
+                .split(|&b| b == PSF2_START_SEQUENCE)
+                .next()
+                .unwrap_or(&[]);
+            let mut rest = singles;
+            while !rest.is_empty() {
+                let len = utf8_len(rest[0]).min(rest.len());
+                if &rest[..len] == encoded {
+                    return Some(index);
+                }
+                rest = &rest[len..];
+            }
+        }
+        None
+    }
+}
+
+fn utf8_len(lead: u8) -> usize {
+    match lead {
+        0xc0..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf7 => 4,
+        _ => 1,
+    }
+}
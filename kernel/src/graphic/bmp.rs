@@ -0,0 +1,118 @@
+use super::{GraphicWriter, PixelColor};
+
+const BI_RGB: u32 = 0;
+const BI_BITFIELDS: u32 = 3;
+
+pub struct Bmp<'a> {
+    data: &'a [u8],
+    width: usize,
+    height: usize,
+    bytes_per_pixel: usize,
+    stride: usize,
+    top_down: bool,
+    has_alpha: bool,
+}
+
+impl<'a> Bmp<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<Self, ()> {
+        if data.len() < 54 || &data[0..2] != b"BM" {
+            return Err(());
+        }
+        let u16_at = |offset: usize| u16::from_le_bytes([data[offset], data[offset + 1]]);
+        let u32_at =
+            |offset: usize| u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap());
+
+        let pixel_offset = u32_at(10) as usize;
+        let header_size = u32_at(14);
+        let width = u32_at(18) as i32;
+        let height = u32_at(22) as i32;
+        let bits_per_pixel = u16_at(28);
+        let compression = u32_at(30);
+
+        if header_size < 40 || width <= 0 || height == 0 {
+            return Err(());
+        }
+        match (bits_per_pixel, compression) {
+            (24, BI_RGB) | (32, BI_RGB) | (32, BI_BITFIELDS) => {}
+            _ => return Err(()),
+        }
+        // Pixels are always read as B, G, R and A bytes, so bit fields are
+        // only accepted when they describe exactly that layout.
+        let mut has_alpha = false;
+        if compression == BI_BITFIELDS {
+            let masks_end = if header_size >= 108 { 70 } else { 66 };
+            if data.len() < masks_end
+                || (u32_at(54), u32_at(58), u32_at(62)) != (0x00ff_0000, 0x0000_ff00, 0x0000_00ff)
+            {
+                return Err(());
+            }
+            if header_size >= 108 {
+                match u32_at(66) {
+                    0 => {}
+                    0xff00_0000 => has_alpha = true,
+                    _ => return Err(()),
+                }
+            }
+        }
+
+        let width = width as usize;
+        let bytes_per_pixel = bits_per_pixel as usize / 8;
+        // Rows are padded to a multiple of four bytes.
+        let stride = (width * bytes_per_pixel + 3) & !3;
+        let height_abs = height.unsigned_abs() as usize;
+        let pixel_end = stride
+            .checked_mul(height_abs)
+            .and_then(|size| size.checked_add(pixel_offset))
+            .ok_or(())?;
+        if pixel_end > data.len() {
+            return Err(());
+        }
+        Ok(Self {
+            data: &data[pixel_offset..pixel_end],
+            width,
+            height: height_abs,
+            bytes_per_pixel,
+            stride,
+            top_down: height < 0,
+            // Plain 32-bit BI_RGB images usually leave the fourth byte zero,
+            // so only V4/V5 headers with an alpha mask carry real alpha.
+            has_alpha,
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    // Returns None for fully transparent pixels of images with alpha.
+    pub fn pixel(&self, x: usize, y: usize) -> Option<PixelColor> {
+        let row = if self.top_down {
+            y
+        } else {
+            self.height - 1 - y
+        };
+        let offset = row * self.stride + x * self.bytes_per_pixel;
+        let pixel = &self.data[offset..offset + self.bytes_per_pixel];
+        if self.has_alpha && pixel[3] == 0 {
+            return None;
+        }
+        Some(PixelColor::new(pixel[2], pixel[1], pixel[0]))
+    }
+}
+
+pub fn draw_bmp(writer: &GraphicWriter, x: usize, y: usize, bmp: &Bmp) {
+    let (screen_width, screen_height) = writer.frame_config.resolution();
+    let width = bmp.width().min(screen_width.saturating_sub(x));
+    let height = bmp.height().min(screen_height.saturating_sub(y));
+    for dy in 0..height {
+        for dx in 0..width {
+            if let Some(color) = bmp.pixel(dx, dy) {
+                writer.write(x + dx, y + dy, color);
+            }
+        }
+    }
+}
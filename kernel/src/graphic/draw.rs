@@ -0,0 +1,200 @@
+use super::{GraphicWriter, PixelColor, Rect};
+
+const MAX_POLYGON_VERTICES: usize = 64;
+
+// Every primitive goes through here so shapes may extend past the screen.
+fn put(writer: &GraphicWriter, x: isize, y: isize, color: PixelColor) {
+    let (width, height) = writer.frame_config.resolution();
+    if x >= 0 && y >= 0 && (x as usize) < width && (y as usize) < height {
+        writer.write(x as usize, y as usize, color);
+    }
+}
+
+fn hline(writer: &GraphicWriter, x0: isize, x1: isize, y: isize, color: PixelColor) {
+    for x in x0.min(x1)..=x0.max(x1) {
+        put(writer, x, y, color);
+    }
+}
+
+pub fn fill_rect(writer: &GraphicWriter, rect: Rect, color: PixelColor) {
+    for y in rect.y..rect.y + rect.height {
+        for x in rect.x..rect.x + rect.width {
+            put(writer, x as isize, y as isize, color);
+        }
+    }
+}
+
+pub fn draw_rect(writer: &GraphicWriter, rect: Rect, color: PixelColor) {
+    if rect.width == 0 || rect.height == 0 {
+        return;
+    }
+    let (x0, y0) = (rect.x as isize, rect.y as isize);
+    let (x1, y1) = (x0 + rect.width as isize - 1, y0 + rect.height as isize - 1);
+    draw_line(writer, (x0, y0), (x1, y0), color);
+    draw_line(writer, (x1, y0), (x1, y1), color);
+    draw_line(writer, (x1, y1), (x0, y1), color);
+    draw_line(writer, (x0, y1), (x0, y0), color);
+}
+
+// Bresenham's line algorithm, valid in all octants.
+pub fn draw_line(
+    writer: &GraphicWriter,
+    from: (isize, isize),
+    to: (isize, isize),
+    color: PixelColor,
+) {
+    let (mut x, mut y) = from;
+    let dx = (to.0 - x).abs();
+    let dy = -(to.1 - y).abs();
+    let sx = if x < to.0 { 1 } else { -1 };
+    let sy = if y < to.1 { 1 } else { -1 };
+    let mut err = dx + dy;
+    loop {
+        put(writer, x, y, color);
+        if x == to.0 && y == to.1 {
+            break;
+        }
+        let e2 = 2 * err;
+        if e2 >= dy {
+            err += dy;
+            x += sx;
+        }
+        if e2 <= dx {
+            err += dx;
+            y += sy;
+        }
+    }
+}
+
+pub fn draw_circle(
+    writer: &GraphicWriter,
+    center: (isize, isize),
+    radius: isize,
+    color: PixelColor,
+) {
+    draw_ellipse(writer, center, radius, radius, color);
+}
+
+pub fn fill_circle(
+    writer: &GraphicWriter,
+    center: (isize, isize),
+    radius: isize,
+    color: PixelColor,
+) {
+    fill_ellipse(writer, center, radius, radius, color);
+}
+
+pub fn draw_ellipse(
+    writer: &GraphicWriter,
+    center: (isize, isize),
+    rx: isize,
+    ry: isize,
+    color: PixelColor,
+) {
+    trace_ellipse(rx, ry, |x, y| {
+        put(writer, center.0 + x, center.1 + y, color);
+        put(writer, center.0 - x, center.1 + y, color);
+        put(writer, center.0 + x, center.1 - y, color);
+        put(writer, center.0 - x, center.1 - y, color);
+    });
+}
+
+pub fn fill_ellipse(
+    writer: &GraphicWriter,
+    center: (isize, isize),
+    rx: isize,
+    ry: isize,
+    color: PixelColor,
+) {
+    trace_ellipse(rx, ry, |x, y| {
+        hline(writer, center.0 - x, center.0 + x, center.1 + y, color);
+        hline(writer, center.0 - x, center.0 + x, center.1 - y, color);
+    });
+}
+
+// Midpoint ellipse algorithm; `plot` receives points of the first quadrant
+// relative to the center and mirrors them itself.
+fn trace_ellipse<F>(rx: isize, ry: isize, mut plot: F)
+where
+    F: FnMut(isize, isize),
+{
+    if rx < 0 || ry < 0 {
+        return;
+    }
+    let (rx2, ry2) = ((rx * rx) as i64, (ry * ry) as i64);
+    let (mut x, mut y) = (0isize, ry);
+    let mut px = 0i64;
+    let mut py = 2 * rx2 * y as i64;
+
+    // Region 1: slope above -1.
+    let mut p = ry2 - rx2 * ry as i64 + rx2 / 4;
+    while px < py {
+        plot(x, y);
+        x += 1;
+        px += 2 * ry2;
+        if p < 0 {
+            p += ry2 + px;
+        } else {
+            y -= 1;
+            py -= 2 * rx2;
+            p += ry2 + px - py;
+        }
+    }
+
+    // Region 2: slope below -1.
+    let (xf, yf) = (x as i64, y as i64);
+    let mut p = ry2 * (2 * xf + 1) * (2 * xf + 1) / 4 + rx2 * (yf - 1) * (yf - 1) - rx2 * ry2;
+    while y >= 0 {
+        plot(x, y);
+        y -= 1;
+        py -= 2 * rx2;
+        if p > 0 {
+            p += rx2 - py;
+        } else {
+            x += 1;
+            px += 2 * ry2;
+            p += rx2 - py + px;
+        }
+    }
+}
+
+pub fn draw_polygon(writer: &GraphicWriter, points: &[(isize, isize)], color: PixelColor) {
+    for (index, &from) in points.iter().enumerate() {
+        let to = points[(index + 1) % points.len()];
+        draw_line(writer, from, to, color);
+    }
+}
+
+// Scanline fill with the even-odd rule. Polygons with more than
+// MAX_POLYGON_VERTICES vertices are rejected since edge crossings are kept
+// on the stack.
+pub fn fill_polygon(
+    writer: &GraphicWriter,
+    points: &[(isize, isize)],
+    color: PixelColor,
+) -> Result<(), ()> {
+    if points.len() < 3 || points.len() > MAX_POLYGON_VERTICES {
+        return Err(());
+    }
+    let top = points.iter().map(|p| p.1).min().unwrap();
+    let bottom = points.iter().map(|p| p.1).max().unwrap();
+
+    let mut crossings = [0isize; MAX_POLYGON_VERTICES];
+    for y in top..=bottom {
+        let mut count = 0;
+        for (index, &(x0, y0)) in points.iter().enumerate() {
+            let (x1, y1) = points[(index + 1) % points.len()];
+            // Half-open on y so shared vertices are only counted once.
+            if (y0 <= y && y < y1) || (y1 <= y && y < y0) {
+                crossings[count] = x0 + (y - y0) * (x1 - x0) / (y1 - y0);
+                count += 1;
+            }
+        }
+        let crossings = &mut crossings[..count];
+        crossings.sort_unstable();
+        for span in crossings.chunks_exact(2) {
+            hline(writer, span[0], span[1], y, color);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,156 @@
+pub mod bmp;
+pub mod draw;
+
+use bootloader::{FrameBufferConfig, PixelFormat};
+
+use core::ptr::{copy, slice_from_raw_parts_mut};
+
+use crate::{
+    device::model::{device_tree, Device, DeviceClass, DeviceState, Resource},
+    sync::OnceLock,
+};
+
+pub static PIXEL_WRITER: OnceLock<GraphicWriter> = OnceLock::new();
+
+#[derive(Clone, Copy)]
+pub struct PixelColor(u8, u8, u8);
+
+impl PixelColor {
+    pub const fn new(red: u8, green: u8, blue: u8) -> Self {
+        Self(red, green, blue)
+    }
+
+    pub const Black: PixelColor = PixelColor(0, 0, 0);
+    pub const White: PixelColor = PixelColor(255, 255, 255);
+    pub const Red: PixelColor = PixelColor(255, 0, 0);
+    pub const Green: PixelColor = PixelColor(0, 255, 0);
+    pub const Blue: PixelColor = PixelColor(0, 0, 255);
+}
+
+#[derive(Clone, Copy)]
+pub struct Rect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Rect {
+    pub const fn new(x: usize, y: usize, width: usize, height: usize) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+}
+
+pub struct GraphicWriter {
+    pub(crate) frame_config: FrameBufferConfig,
+    write_fn: fn(&GraphicWriter, usize, usize, PixelColor),
+}
+
+impl GraphicWriter {
+    pub fn new(frame_config: FrameBufferConfig) -> Self {
+        let write_fn = match frame_config.pixel_format() {
+            PixelFormat::RGBReserved8 => rgb_write,
+            PixelFormat::BGRReserved8 => bgr_write,
+            _ => panic!(),
+        };
+        Self {
+            frame_config,
+            write_fn,
+        }
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> &'static mut [u8] {
+        let position: usize = x + self.frame_config.stride() * y;
+        let address = self.frame_config.address() + position as u64 * 4;
+        unsafe { &mut *slice_from_raw_parts_mut(address as *mut u8, 4) }
+    }
+
+    pub fn write(&self, x: usize, y: usize, color: PixelColor) {
+        (self.write_fn)(self, x, y, color)
+    }
+
+    // Moves the contents of `area` by (dx, dy) with a row-wise memmove. The
+    // strip uncovered by the move keeps its old pixels; callers repaint it.
+    pub fn scroll(&self, dx: isize, dy: isize, area: Rect) {
+        let (screen_width, screen_height) = self.frame_config.resolution();
+        let area_width = area.width.min(screen_width.saturating_sub(area.x));
+        let area_height = area.height.min(screen_height.saturating_sub(area.y));
+        let width = area_width.saturating_sub(dx.unsigned_abs());
+        let height = area_height.saturating_sub(dy.unsigned_abs());
+        if width == 0 || height == 0 {
+            return;
+        }
+
+        let (src_x, dst_x) = match dx {
+            0.. => (area.x, area.x + dx as usize),
+            _ => (area.x + dx.unsigned_abs(), area.x),
+        };
+        let (src_y, dst_y) = match dy {
+            0.. => (area.y, area.y + dy as usize),
+            _ => (area.y + dy.unsigned_abs(), area.y),
+        };
+
+        let stride = self.frame_config.stride() * 4;
+        let base = self.frame_config.address() as *mut u8;
+        let copy_row = |row: usize| unsafe {
+            copy(
+                base.add((src_y + row) * stride + src_x * 4),
+                base.add((dst_y + row) * stride + dst_x * 4),
+                width * 4,
+            )
+        };
+        if dy > 0 {
+            (0..height).rev().for_each(copy_row);
+        } else {
+            (0..height).for_each(copy_row);
+        }
+    }
+
+    pub fn clean(&self) {
+        for x in 0..self.frame_config.resolution().0 {
+            for y in 0..self.frame_config.resolution().1 {
+                self.write(x, y, PixelColor::Black);
+            }
+        }
+    }
+}
+
+fn bgr_write(writer: &GraphicWriter, x: usize, y: usize, color: PixelColor) {
+    let pixel = writer.pixel(x, y);
+    pixel[0] = color.2;
+    pixel[1] = color.1;
+    pixel[2] = color.0;
+}
+
+fn rgb_write(writer: &GraphicWriter, x: usize, y: usize, color: PixelColor) {
+    let pixel = writer.pixel(x, y);
+    pixel[0] = color.0;
+    pixel[1] = color.1;
+    pixel[2] = color.2;
+}
+
+pub fn graphic(frame_config: FrameBufferConfig) -> &'static GraphicWriter {
+    PIXEL_WRITER.get_or_init(|| GraphicWriter::new(frame_config))
+}
+
+crate::initcall!(FB_INITCALL, "fb0", Device, [], || {
+    let writer = PIXEL_WRITER.get().ok_or(())?;
+    let mut tree = device_tree().lock();
+    let fb = tree.register(
+        Device::new(DeviceClass::Display, None)
+            .with_name(format_args!("fb0"))
+            .with_resource(Resource::Mmio {
+                base: writer.frame_config.address(),
+                size: writer.frame_config.size() as u64,
+            }),
+    )?;
+    tree.set_state(fb, DeviceState::Active)
+});
+
+unsafe impl Send for GraphicWriter {}
+unsafe impl Sync for GraphicWriter {}
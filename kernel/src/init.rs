@@ -0,0 +1,209 @@
+use core::slice;
+
+use log::{info, warn};
+
+extern "C" {
+    static __initcall_start: u8;
+    static __initcall_end: u8;
+}
+
+const MAX_INITCALLS: usize = 32;
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum InitStage {
+    Memory,
+    Irq,
+    Bus,
+    Device,
+    Fs,
+    Ui,
+}
+
+pub struct InitCall {
+    pub name: &'static str,
+    pub stage: InitStage,
+    pub depends: &'static [&'static str],
+    pub init: fn() -> Result<(), ()>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum InitStatus {
+    Pending,
+    Done,
+    Failed,
+    Skipped,
+}
+
+// Places an InitCall in the .initcall section, where run_initcalls() picks
+// it up. Dependencies name other init calls of the same or an earlier stage.
+#[macro_export]
+macro_rules! initcall {
+    ($static:ident, $name:literal, $stage:ident, [$($dep:literal),* $(,)?], $init:expr) => {
+        #[used]
+        #[link_section = ".initcall"]
+        static $static: $crate::init::InitCall = $crate::init::InitCall {
+            name: $name,
+            stage: $crate::init::InitStage::$stage,
+            depends: &[$($dep),*],
+            init: $init,
+        };
+    };
+}
+
+fn initcalls() -> &'static [InitCall] {
+    unsafe {
+        let start = &__initcall_start as *const u8 as *const InitCall;
+        let end = &__initcall_end as *const u8 as *const InitCall;
+        slice::from_raw_parts(start, end.offset_from(start) as usize)
+    }
+}
+
+struct InitTable {
+    calls: [Option<&'static InitCall>; MAX_INITCALLS],
+    status: [InitStatus; MAX_INITCALLS],
+    len: usize,
+}
+
+impl InitTable {
+    // Calls are ordered by stage and then by name so the boot order does not
+    // depend on link order.
+    fn new(calls: &'static [InitCall]) -> Result<Self, ()> {
+        if calls.len() > MAX_INITCALLS {
+            return Err(());
+        }
+        let mut table = Self {
+            calls: [None; MAX_INITCALLS],
+            status: [InitStatus::Pending; MAX_INITCALLS],
+            len: calls.len(),
+        };
+        for (index, call) in calls.iter().enumerate() {
+            let mut position = index;
+            while position > 0 {
+                let prev = table.calls[position - 1].unwrap();
+                if (prev.stage, prev.name) <= (call.stage, call.name) {
+                    break;
+                }
+                table.calls[position] = table.calls[position - 1];
+                position -= 1;
+            }
+            table.calls[position] = Some(call);
+        }
+        Ok(table)
+    }
+
+    fn call(&self, index: usize) -> &'static InitCall {
+        self.calls[index].unwrap()
+    }
+
+    fn find(&self, name: &str) -> Option<usize> {
+        (0..self.len).find(|&index| self.call(index).name == name)
+    }
+
+    // Every dependency has to exist and must not run in a later stage. Calls
+    // that break this are marked failed up front, so they and their
+    // dependents are skipped while the rest of the boot goes on.
+    fn validate(&mut self) {
+        for index in 0..self.len {
+            let call = self.call(index);
+            if self.find(call.name) != Some(index) {
+                warn!("initcall {}: registered twice", call.name);
+                self.status[index] = InitStatus::Failed;
+            }
+            for &dep in call.depends {
+                match self.find(dep) {
+                    None => {
+                        warn!("initcall {}: unknown dependency {}", call.name, dep);
+                        self.status[index] = InitStatus::Failed;
+                    }
+                    Some(dep_index) if self.call(dep_index).stage > call.stage => {
+                        warn!("initcall {}: {} runs in a later stage", call.name, dep);
+                        self.status[index] = InitStatus::Failed;
+                    }
+                    Some(_) => {}
+                }
+            }
+        }
+    }
+
+    fn run_stage(&mut self, stage: InitStage) {
+        let in_stage = |index: &usize| self.call(*index).stage == stage;
+        let (Some(first), Some(last)) =
+            ((0..self.len).find(in_stage), (0..self.len).rfind(in_stage))
+        else {
+            return;
+        };
+
+        // Keep sweeping the stage until nothing becomes runnable. Whatever is
+        // still pending after that waits on a cycle.
+        loop {
+            let mut progress = false;
+            for index in first..=last {
+                if self.status[index] != InitStatus::Pending {
+                    continue;
+                }
+                let call = self.call(index);
+                let mut ready = true;
+                for &dep in call.depends {
+                    match self.find(dep).map(|dep_index| self.status[dep_index]) {
+                        Some(InitStatus::Done) => {}
+                        Some(InitStatus::Pending) => ready = false,
+                        _ => {
+                            warn!("initcall {}: skipped, {} did not come up", call.name, dep);
+                            self.status[index] = InitStatus::Skipped;
+                            ready = false;
+                            progress = true;
+                            break;
+                        }
+                    }
+                }
+                if !ready {
+                    continue;
+                }
+                self.status[index] = match (call.init)() {
+                    Ok(()) => InitStatus::Done,
+                    Err(()) => {
+                        warn!("initcall {}: failed", call.name);
+                        InitStatus::Failed
+                    }
+                };
+                progress = true;
+            }
+            if !progress {
+                break;
+            }
+        }
+
+        for index in first..=last {
+            if self.status[index] == InitStatus::Pending {
+                warn!("initcall {}: dependency cycle", self.call(index).name);
+                self.status[index] = InitStatus::Skipped;
+            }
+        }
+    }
+}
+
+pub fn run_initcalls() -> Result<(), ()> {
+    let mut table = InitTable::new(initcalls())?;
+    table.validate();
+    for stage in [
+        InitStage::Memory,
+        InitStage::Irq,
+        InitStage::Bus,
+        InitStage::Device,
+        InitStage::Fs,
+        InitStage::Ui,
+    ] {
+        table.run_stage(stage);
+    }
+
+    let done = table.status[..table.len]
+        .iter()
+        .filter(|&&status| status == InitStatus::Done)
+        .count();
+    info!("initcalls: {}/{} succeeded", done, table.len);
+    if done == table.len {
+        Ok(())
+    } else {
+        Err(())
+    }
+}
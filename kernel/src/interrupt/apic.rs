@@ -0,0 +1,66 @@
+use core::{
+    arch::asm,
+    ptr::{read_volatile, write_volatile},
+};
+
+const IA32_APIC_BASE: u32 = 0x1b;
+const X2APIC_ENABLE: u64 = 1 << 10;
+const X2APIC_MSR_BASE: u32 = 0x800;
+
+const REGISTER_ID: u32 = 0x20;
+const REGISTER_EOI: u32 = 0xb0;
+
+pub fn read_msr(msr: u32) -> u64 {
+    let (low, high): (u32, u32);
+    unsafe {
+        asm!("rdmsr", in("ecx") msr, out("eax") low, out("edx") high, options(nomem, nostack))
+    };
+    (high as u64) << 32 | low as u64
+}
+
+pub fn write_msr(msr: u32, value: u64) {
+    unsafe {
+        asm!(
+            "wrmsr",
+            in("ecx") msr,
+            in("eax") value as u32,
+            in("edx") (value >> 32) as u32,
+            options(nostack)
+        )
+    };
+}
+
+// The firmware may hand over the local APIC in either xAPIC (memory mapped)
+// or x2APIC (MSR) mode, so every access checks which one is active.
+fn read_register(register: u32) -> u32 {
+    let base = read_msr(IA32_APIC_BASE);
+    if base & X2APIC_ENABLE != 0 {
+        read_msr(X2APIC_MSR_BASE + (register >> 4)) as u32
+    } else {
+        let address = (base & 0x000f_ffff_ffff_f000) + register as u64;
+        unsafe { read_volatile(address as *const u32) }
+    }
+}
+
+fn write_register(register: u32, value: u32) {
+    let base = read_msr(IA32_APIC_BASE);
+    if base & X2APIC_ENABLE != 0 {
+        write_msr(X2APIC_MSR_BASE + (register >> 4), value as u64);
+    } else {
+        let address = (base & 0x000f_ffff_ffff_f000) + register as u64;
+        unsafe { write_volatile(address as *mut u32, value) };
+    }
+}
+
+pub fn lapic_id() -> u32 {
+    let id = read_register(REGISTER_ID);
+    if read_msr(IA32_APIC_BASE) & X2APIC_ENABLE != 0 {
+        id
+    } else {
+        id >> 24
+    }
+}
+
+pub fn end_of_interrupt() {
+    write_register(REGISTER_EOI, 0);
+}
@@ -0,0 +1,110 @@
+use core::{arch::asm, mem::size_of, ptr::addr_of};
+
+use crate::sync::Mutex;
+
+pub const KERNEL_CODE_SELECTOR: u16 = 0x08;
+pub const KERNEL_DATA_SELECTOR: u16 = 0x10;
+const TSS_SELECTOR: u16 = 0x18;
+
+// IST slots as numbered in the IDT, zero means no stack switch.
+pub const DOUBLE_FAULT_IST: u8 = 1;
+pub const NMI_IST: u8 = 2;
+pub const MACHINE_CHECK_IST: u8 = 3;
+pub const PAGE_FAULT_IST: u8 = 4;
+
+const IST_NAMES: [&str; 4] = ["double fault", "NMI", "machine check", "page fault"];
+const IST_STACK_SIZE: usize = 16 * 1024;
+
+#[repr(C, align(16))]
+struct Stack([u8; IST_STACK_SIZE]);
+
+#[repr(C, packed)]
+struct Tss {
+    reserved0: u32,
+    rsp: [u64; 3],
+    reserved1: u64,
+    ist: [u64; 7],
+    reserved2: u64,
+    reserved3: u16,
+    iomap_base: u16,
+}
+
+#[repr(C, packed)]
+struct GdtPointer {
+    limit: u16,
+    base: u64,
+}
+
+// Only the CPU writes to these, and the fault path looks up their ranges, so
+// they are never locked.
+static mut IST_STACKS: [Stack; 4] = [const { Stack([0; IST_STACK_SIZE]) }; 4];
+static TSS: Mutex<Tss> = Mutex::new(Tss {
+    reserved0: 0,
+    rsp: [0; 3],
+    reserved1: 0,
+    ist: [0; 7],
+    reserved2: 0,
+    reserved3: 0,
+    iomap_base: size_of::<Tss>() as u16,
+});
+// Null, kernel code, kernel data and the two halves of the TSS descriptor.
+static GDT: Mutex<[u64; 5]> = Mutex::new([0, 0x00af_9a00_0000_ffff, 0x00cf_9200_0000_ffff, 0, 0]);
+
+fn ist_range(index: usize) -> (u64, u64) {
+    let base = unsafe { addr_of!(IST_STACKS[index]) } as u64;
+    (base, base + IST_STACK_SIZE as u64)
+}
+
+// Names the IST stack an address lies on, so fault reports can tell a
+// double fault caused by a kernel stack overflow apart from one that was not.
+pub fn ist_stack_of(address: u64) -> Option<&'static str> {
+    (0..IST_NAMES.len())
+        .find(|&index| {
+            let (start, end) = ist_range(index);
+            (start..end).contains(&address)
+        })
+        .map(|index| IST_NAMES[index])
+}
+
+pub fn init_gdt() {
+    let mut tss = TSS.lock();
+    for index in 0..IST_NAMES.len() {
+        tss.ist[index] = ist_range(index).1;
+    }
+    let base = &*tss as *const Tss as u64;
+    let limit = (size_of::<Tss>() - 1) as u64;
+
+    let mut gdt = GDT.lock();
+    gdt[3] = (limit & 0xffff)
+        | (base & 0x00ff_ffff) << 16
+        | 0x89 << 40
+        | (limit & 0x000f_0000) << 32
+        | (base & 0xff00_0000) << 32;
+    gdt[4] = base >> 32;
+
+    let pointer = GdtPointer {
+        limit: (size_of::<[u64; 5]>() - 1) as u16,
+        base: gdt.as_ptr() as u64,
+    };
+    unsafe {
+        asm!(
+            "lgdt [{pointer}]",
+            "push {code}",
+            "lea {scratch}, [rip + 2f]",
+            "push {scratch}",
+            "retfq",
+            "2:",
+            "mov ds, {data:x}",
+            "mov es, {data:x}",
+            "mov fs, {data:x}",
+            "mov gs, {data:x}",
+            "mov ss, {data:x}",
+            "ltr {tss:x}",
+            pointer = in(reg) &pointer,
+            code = in(reg) KERNEL_CODE_SELECTOR as u64,
+            data = in(reg) KERNEL_DATA_SELECTOR as u64,
+            tss = in(reg) TSS_SELECTOR as u64,
+            scratch = out(reg) _,
+        )
+    };
+}
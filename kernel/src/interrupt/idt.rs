@@ -0,0 +1,160 @@
+use core::{
+    arch::{asm, global_asm},
+    mem::size_of,
+};
+
+use crate::sync::Mutex;
+
+use super::{
+    gdt::{DOUBLE_FAULT_IST, KERNEL_CODE_SELECTOR, MACHINE_CHECK_IST, NMI_IST, PAGE_FAULT_IST},
+    interrupt_dispatch,
+};
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct IdtEntry {
+    offset_low: u16,
+    selector: u16,
+    ist: u8,
+    attributes: u8,
+    offset_middle: u16,
+    offset_high: u32,
+    reserved: u32,
+}
+
+#[repr(C, packed)]
+struct IdtPointer {
+    limit: u16,
+    base: u64,
+}
+
+// Present, ring 0, 64-bit interrupt gate so handlers start with interrupts
+// masked.
+const INTERRUPT_GATE: u8 = 0x8e;
+const STUB_SIZE: usize = 16;
+
+static IDT: Mutex<[IdtEntry; 256]> = Mutex::new([IdtEntry::MISSING; 256]);
+
+extern "C" {
+    static interrupt_stubs: u8;
+}
+
+// One 16 byte stub per vector. Vectors for which the CPU does not push an
+// error code push a zero instead, so every handler sees the same frame. The
+// common path saves the general purpose and SSE registers, since interrupted
+// code may be using either, and hands the frame to interrupt_dispatch.
+global_asm!(
+    r#"
+    .section .text
+    .global interrupt_stubs
+    .align 16
+interrupt_stubs:
+    .set isr_vector, 0
+    .rept 256
+    .align 16
+    .if isr_vector == 8 || (isr_vector >= 10 && isr_vector <= 14) || isr_vector == 17 || isr_vector == 21 || isr_vector == 29 || isr_vector == 30
+    .else
+    pushq $0
+    .endif
+    pushq $isr_vector
+    jmp .Linterrupt_common
+    .set isr_vector, isr_vector + 1
+    .endr
+
+.Linterrupt_common:
+    pushq %rax
+    pushq %rbx
+    pushq %rcx
+    pushq %rdx
+    pushq %rsi
+    pushq %rdi
+    pushq %rbp
+    pushq %r8
+    pushq %r9
+    pushq %r10
+    pushq %r11
+    pushq %r12
+    pushq %r13
+    pushq %r14
+    pushq %r15
+    subq $512, %rsp
+    fxsave (%rsp)
+    leaq 512(%rsp), %rdi
+    cld
+    call {dispatch}
+    fxrstor (%rsp)
+    addq $512, %rsp
+    popq %r15
+    popq %r14
+    popq %r13
+    popq %r12
+    popq %r11
+    popq %r10
+    popq %r9
+    popq %r8
+    popq %rbp
+    popq %rdi
+    popq %rsi
+    popq %rdx
+    popq %rcx
+    popq %rbx
+    popq %rax
+    addq $16, %rsp
+    iretq
+"#,
+    dispatch = sym interrupt_dispatch,
+    options(att_syntax)
+);
+
+impl IdtEntry {
+    const MISSING: IdtEntry = IdtEntry {
+        offset_low: 0,
+        selector: 0,
+        ist: 0,
+        attributes: 0,
+        offset_middle: 0,
+        offset_high: 0,
+        reserved: 0,
+    };
+
+    fn new(handler: u64, selector: u16, ist: u8) -> Self {
+        Self {
+            offset_low: handler as u16,
+            selector,
+            ist,
+            attributes: INTERRUPT_GATE,
+            offset_middle: (handler >> 16) as u16,
+            offset_high: (handler >> 32) as u32,
+            reserved: 0,
+        }
+    }
+}
+
+// Faults that may arrive on a broken kernel stack get a stack of their own.
+fn ist_for(vector: usize) -> u8 {
+    match vector {
+        2 => NMI_IST,
+        8 => DOUBLE_FAULT_IST,
+        14 => PAGE_FAULT_IST,
+        18 => MACHINE_CHECK_IST,
+        _ => 0,
+    }
+}
+
+pub fn init_idt() {
+    let stubs = unsafe { &interrupt_stubs as *const u8 as u64 };
+    let mut idt = IDT.lock();
+    for (vector, entry) in idt.iter_mut().enumerate() {
+        *entry = IdtEntry::new(
+            stubs + (vector * STUB_SIZE) as u64,
+            KERNEL_CODE_SELECTOR,
+            ist_for(vector),
+        );
+    }
+
+    let pointer = IdtPointer {
+        limit: (size_of::<[IdtEntry; 256]>() - 1) as u16,
+        base: idt.as_ptr() as u64,
+    };
+    unsafe { asm!("lidt [{}]", in(reg) &pointer, options(readonly, nostack, preserves_flags)) };
+}
@@ -0,0 +1,185 @@
+use core::ptr::{read_volatile, write_volatile};
+
+use crate::{
+    acpi::{madt_entries, MadtEntry},
+    device::{
+        model::{device_tree, Device, DeviceClass, DeviceState, Resource},
+        Port,
+    },
+    sync::Mutex,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Trigger {
+    Edge,
+    Level,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Polarity {
+    ActiveHigh,
+    ActiveLow,
+}
+
+#[derive(Clone, Copy)]
+pub struct IoApic {
+    pub id: u8,
+    pub address: u64,
+    pub gsi_base: u32,
+    pub count: u32,
+}
+
+static IOAPICS: Mutex<[Option<IoApic>; 4]> = Mutex::new([None; 4]);
+
+const REGISTER_VERSION: u32 = 0x01;
+const REGISTER_REDIRECTION: u32 = 0x10;
+const REDIRECTION_MASKED: u32 = 1 << 16;
+
+impl IoApic {
+    fn read(&self, register: u32) -> u32 {
+        unsafe {
+            write_volatile(self.address as *mut u32, register);
+            read_volatile((self.address + 0x10) as *const u32)
+        }
+    }
+
+    fn write(&self, register: u32, value: u32) {
+        unsafe {
+            write_volatile(self.address as *mut u32, register);
+            write_volatile((self.address + 0x10) as *mut u32, value);
+        }
+    }
+
+    fn contains(&self, gsi: u32) -> bool {
+        (self.gsi_base..self.gsi_base + self.count).contains(&gsi)
+    }
+
+    fn write_redirection(&self, gsi: u32, low: u32, high: u32) {
+        let register = REGISTER_REDIRECTION + (gsi - self.gsi_base) * 2;
+        // Mask first so the entry never fires half written.
+        self.write(register, REDIRECTION_MASKED);
+        self.write(register + 1, high);
+        self.write(register, low);
+    }
+}
+
+fn ioapic_for(gsi: u32) -> Option<IoApic> {
+    IOAPICS
+        .lock()
+        .iter()
+        .flatten()
+        .find(|ioapic| ioapic.contains(gsi))
+        .copied()
+}
+
+pub fn route(
+    gsi: u32,
+    vector: u8,
+    dest_apic: u8,
+    trigger: Trigger,
+    polarity: Polarity,
+) -> Result<(), ()> {
+    let ioapic = ioapic_for(gsi).ok_or(())?;
+    let mut low = vector as u32;
+    if polarity == Polarity::ActiveLow {
+        low |= 1 << 13;
+    }
+    if trigger == Trigger::Level {
+        low |= 1 << 15;
+    }
+    ioapic.write_redirection(gsi, low, (dest_apic as u32) << 24);
+    Ok(())
+}
+
+// Moves an already routed entry to another local APIC, leaving vector,
+// trigger and polarity alone.
+pub fn set_destination(gsi: u32, dest_apic: u8) -> Result<(), ()> {
+    let ioapic = ioapic_for(gsi).ok_or(())?;
+    let register = REGISTER_REDIRECTION + (gsi - ioapic.gsi_base) * 2;
+    let low = ioapic.read(register);
+    ioapic.write_redirection(gsi, low, (dest_apic as u32) << 24);
+    Ok(())
+}
+
+pub fn mask(gsi: u32) -> Result<(), ()> {
+    let ioapic = ioapic_for(gsi).ok_or(())?;
+    ioapic.write_redirection(gsi, REDIRECTION_MASKED, 0);
+    Ok(())
+}
+
+// ISA interrupts are identity mapped, edge triggered and active high unless
+// the MADT carries an override for them.
+pub fn isa_irq(irq: u8) -> (u32, Trigger, Polarity) {
+    let mut routing = (irq as u32, Trigger::Edge, Polarity::ActiveHigh);
+    for entry in madt_entries() {
+        if let MadtEntry::InterruptOverride { source, gsi, flags } = entry {
+            if source != irq {
+                continue;
+            }
+            routing.0 = gsi;
+            if flags & 0x03 == 0x03 {
+                routing.2 = Polarity::ActiveLow;
+            }
+            if (flags >> 2) & 0x03 == 0x03 {
+                routing.1 = Trigger::Level;
+            }
+        }
+    }
+    routing
+}
+
+pub fn route_isa_irq(irq: u8, vector: u8, dest_apic: u8) -> Result<(), ()> {
+    let (gsi, trigger, polarity) = isa_irq(irq);
+    route(gsi, vector, dest_apic, trigger, polarity)
+}
+
+// Masks the legacy PICs and every I/O APIC input so nothing is delivered
+// until a driver routes it.
+pub fn init_ioapic() -> Result<(), ()> {
+    Port::new(0x21).out8(0xff);
+    Port::new(0xa1).out8(0xff);
+
+    let mut ioapics = IOAPICS.lock();
+    let mut slots = ioapics.iter_mut();
+    for entry in madt_entries() {
+        let MadtEntry::IoApic {
+            id,
+            address,
+            gsi_base,
+        } = entry
+        else {
+            continue;
+        };
+        let Some(slot) = slots.next() else { break };
+        let mut ioapic = IoApic {
+            id,
+            address: address as u64,
+            gsi_base,
+            count: 0,
+        };
+        ioapic.count = ((ioapic.read(REGISTER_VERSION) >> 16) & 0xff) + 1;
+        for gsi in gsi_base..gsi_base + ioapic.count {
+            ioapic.write_redirection(gsi, REDIRECTION_MASKED, 0);
+        }
+        *slot = Some(ioapic);
+    }
+
+    if ioapics.iter().all(|ioapic| ioapic.is_none()) {
+        return Err(());
+    }
+    let mut tree = device_tree().lock();
+    for (index, ioapic) in ioapics.iter().flatten().enumerate() {
+        let id = tree.register(
+            Device::new(DeviceClass::Other, None)
+                .with_name(format_args!("ioapic{index}"))
+                .with_resource(Resource::Mmio {
+                    base: ioapic.address,
+                    size: 0x20,
+                }),
+        )?;
+        tree.set_state(id, DeviceState::Active)?;
+    }
+    Ok(())
+}
+
+crate::initcall!(IOAPIC_INITCALL, "ioapic", Irq, ["idt"], init_ioapic);
@@ -0,0 +1,172 @@
+pub mod apic;
+pub mod gdt;
+pub mod idt;
+pub mod ioapic;
+
+use core::{
+    arch::asm,
+    mem::transmute,
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+};
+
+use crate::println;
+
+use self::{
+    apic::end_of_interrupt,
+    gdt::{init_gdt, ist_stack_of},
+    idt::init_idt,
+};
+
+pub type IrqHandler = fn(&mut Context);
+
+// Register state saved by the common interrupt stub, lowest address first.
+#[repr(C)]
+#[derive(Debug)]
+pub struct Context {
+    pub r15: u64,
+    pub r14: u64,
+    pub r13: u64,
+    pub r12: u64,
+    pub r11: u64,
+    pub r10: u64,
+    pub r9: u64,
+    pub r8: u64,
+    pub rbp: u64,
+    pub rdi: u64,
+    pub rsi: u64,
+    pub rdx: u64,
+    pub rcx: u64,
+    pub rbx: u64,
+    pub rax: u64,
+    pub vector: u64,
+    pub error_code: u64,
+    pub rip: u64,
+    pub cs: u64,
+    pub rflags: u64,
+    pub rsp: u64,
+    pub ss: u64,
+}
+
+// Vectors below 32 are CPU exceptions, 0xff is kept for the spurious
+// interrupt.
+pub const FIRST_IRQ_VECTOR: u8 = 0x20;
+pub const SPURIOUS_VECTOR: u8 = 0xff;
+const FIRST_DYNAMIC_VECTOR: u8 = 0x40;
+const LAST_DYNAMIC_VECTOR: u8 = 0xef;
+
+// Handlers are stored as plain addresses so the dispatcher never has to take
+// a lock from interrupt context. Zero means no handler.
+static HANDLERS: [AtomicUsize; 256] = [const { AtomicUsize::new(0) }; 256];
+static COUNTS: [AtomicU64; 256] = [const { AtomicU64::new(0) }; 256];
+
+const EXCEPTION_NAMES: [&str; 22] = [
+    "divide error",
+    "debug",
+    "non-maskable interrupt",
+    "breakpoint",
+    "overflow",
+    "bound range exceeded",
+    "invalid opcode",
+    "device not available",
+    "double fault",
+    "coprocessor segment overrun",
+    "invalid TSS",
+    "segment not present",
+    "stack-segment fault",
+    "general protection fault",
+    "page fault",
+    "reserved",
+    "x87 floating-point exception",
+    "alignment check",
+    "machine check",
+    "SIMD floating-point exception",
+    "virtualization exception",
+    "control protection exception",
+];
+
+pub fn register_irq(vector: u8, handler: IrqHandler) -> Result<(), ()> {
+    if vector < FIRST_IRQ_VECTOR || vector == SPURIOUS_VECTOR {
+        return Err(());
+    }
+    HANDLERS[vector as usize]
+        .compare_exchange(0, handler as usize, Ordering::AcqRel, Ordering::Acquire)
+        .map(|_| ())
+        .map_err(|_| ())
+}
+
+// Picks a free vector for drivers that do not care which one they get.
+pub fn allocate_irq(handler: IrqHandler) -> Result<u8, ()> {
+    (FIRST_DYNAMIC_VECTOR..=LAST_DYNAMIC_VECTOR)
+        .find(|&vector| register_irq(vector, handler).is_ok())
+        .ok_or(())
+}
+
+pub fn unregister_irq(vector: u8) {
+    HANDLERS[vector as usize].store(0, Ordering::Release);
+}
+
+pub fn irq_count(vector: u8) -> u64 {
+    COUNTS[vector as usize].load(Ordering::Relaxed)
+}
+
+pub fn print_irq_stats() {
+    for vector in 0..=u8::MAX {
+        let count = irq_count(vector);
+        if count == 0 {
+            continue;
+        }
+        let kind = match vector {
+            0..=0x1f => EXCEPTION_NAMES
+                .get(vector as usize)
+                .copied()
+                .unwrap_or("exception"),
+            SPURIOUS_VECTOR => "spurious",
+            _ if HANDLERS[vector as usize].load(Ordering::Relaxed) != 0 => "irq",
+            _ => "unhandled",
+        };
+        println!("{vector:3}: {count:10} {kind}");
+    }
+}
+
+fn read_cr2() -> u64 {
+    let cr2: u64;
+    unsafe { asm!("mov {}, cr2", out(reg) cr2, options(nomem, nostack, preserves_flags)) };
+    cr2
+}
+
+extern "C" fn interrupt_dispatch(context: &mut Context) {
+    let vector = context.vector as u8;
+    COUNTS[vector as usize].fetch_add(1, Ordering::Relaxed);
+
+    let handler = HANDLERS[vector as usize].load(Ordering::Acquire);
+    if handler != 0 {
+        let handler: IrqHandler = unsafe { transmute(handler) };
+        handler(context);
+    } else if vector < FIRST_IRQ_VECTOR {
+        let name = EXCEPTION_NAMES
+            .get(vector as usize)
+            .copied()
+            .unwrap_or("reserved");
+        if vector == 14 {
+            println!("faulting address: 0x{:016X}", read_cr2());
+        }
+        if let Some(stack) = ist_stack_of(context as *const Context as u64) {
+            println!("running on the {} IST stack", stack);
+        }
+        panic!(
+            "{} (vector {}, error 0x{:X}) at 0x{:016X}\n{:#X?}",
+            name, vector, context.error_code, context.rip, context
+        );
+    }
+
+    // The local APIC does not expect an EOI for spurious interrupts.
+    if vector >= FIRST_IRQ_VECTOR && vector != SPURIOUS_VECTOR {
+        end_of_interrupt();
+    }
+}
+
+crate::initcall!(IDT_INITCALL, "idt", Irq, [], || {
+    init_gdt();
+    init_idt();
+    Ok(())
+});
@@ -3,11 +3,19 @@
 #![feature(lazy_cell)]
 #![feature(generic_arg_infer)]
 
+pub mod acpi;
 pub mod console;
+pub mod debug;
 pub mod device;
+pub mod event;
 pub mod font;
 pub mod graphic;
+pub mod init;
+pub mod interrupt;
+pub mod page;
+pub mod selftest;
 pub mod sync;
+pub mod sys;
 
 use bootloader::{BootInfo, FrameBufferConfig, PixelFormat};
 use core::panic::PanicInfo;
@@ -31,6 +39,14 @@ macro_rules! entry_point {
 
 #[panic_handler]
 fn panic(info: &PanicInfo) -> ! {
+    // Jump back to the live screen in case the console was scrolled back, and
+    // make sure output deferred by an open batch is not lost.
+    {
+        let mut console = console::CONSOLE.lock();
+        console.flush();
+        console.scroll_down(usize::MAX);
+    }
+    println!("\x1b[97;41m KERNEL PANIC \x1b[0m");
     error!("{}", info);
     loop {}
 }
@@ -5,12 +5,19 @@ use core::arch::asm;
 
 use bootloader::{BootInfo, FrameBufferConfig, PixelFormat};
 use kernel::{
+    acpi::init_acpi,
     console::{init_console, Console},
-    device::pci::{init_pci, Pci, PciDevice},
+    device::{
+        initrd::init_initrd,
+        model::lsdev,
+        pci::{init_pci, lspci, Bar, Pci, PciDevice},
+    },
     entry_point,
+    event::{record_event, Event},
     font::write_ascii,
     graphic::{graphic, GraphicWriter, PixelColor},
-    println,
+    init::run_initcalls,
+    println, sys,
 };
 use log::{info, warn};
 
@@ -35,27 +42,37 @@ fn swithc_ehci_to_xhci(xhc_dev: &PciDevice) {
 
 fn kernel_main(boot_info: BootInfo) {
     let (height, width) = boot_info.frame_config.resolution();
+    let rsdp = boot_info.rsdp();
 
+    if let Some(config) = boot_info.initrd() {
+        init_initrd(config);
+    }
     let pixel_writer = graphic(boot_info.frame_config);
     pixel_writer.clean();
 
     init_console(pixel_writer, PixelColor::Black, PixelColor::White);
+    info!("{}", sys::uname());
+    record_event(Event::Boot);
 
-    let pci = init_pci();
-    for dev in pci.lock().device_iter() {
-        let vendor_id = dev.read_vendor_id();
-        let class_code = dev.class_code;
-        info!(
-            "{}.{}.{}: vend {:04X}, class {:02X}{:02X}, head {:02x}",
-            dev.bus, dev.dev, dev.func, vendor_id, class_code.base, class_code.sub, dev.header_type
-        );
+    match rsdp {
+        Some(rsdp) if init_acpi(rsdp).is_err() => warn!("invalid ACPI RSDP at 0x{rsdp:016X}"),
+        Some(_) => {}
+        None => warn!("no ACPI tables, PCI extended config space is unavailable"),
+    }
+
+    if run_initcalls().is_err() {
+        warn!("some subsystems failed to initialize");
     }
+    let pci = init_pci();
+    lsdev();
+
+    lspci();
 
     let mut xhc_dev: Option<PciDevice> = None;
     for &dev in pci.lock().device_iter() {
         if dev.class_code.is_class(0x0c, 0x03, 0x30) {
             xhc_dev = Some(dev);
-            if dev.read_vendor_id() == 0x8086 {
+            if dev.vendor_id == 0x8086 {
                 break;
             }
         }
@@ -63,12 +80,17 @@ fn kernel_main(boot_info: BootInfo) {
 
     if let Some(dev) = xhc_dev {
         info!("xHC has been found: {}.{}.{}", dev.bus, dev.dev, dev.func);
-        let xhc_bar = dev.read_bar(0);
-        info!("Read BAR0: 0x{xhc_bar:016X}");
-        let xhc_mmio_base = xhc_bar & (!0xfu64);
-        info!("xHC mmio base: 0x{xhc_mmio_base:016X}");
+        match dev.bars[0] {
+            Some(Bar::Memory { base, size, .. }) => {
+                info!("xHC mmio base: 0x{base:016X}, size 0x{size:X}");
+                // The controller reads its rings straight from memory.
+                dev.enable_memory_space();
+                dev.enable_bus_master();
+            }
+            _ => warn!("xHC BAR0 is not a memory BAR"),
+        }
 
-        if dev.read_vendor_id() == 0x8086 {
+        if dev.vendor_id == 0x8086 {
             swithc_ehci_to_xhci(&dev);
         }
     } else {
@@ -0,0 +1,132 @@
+use core::arch::asm;
+
+use crate::println;
+
+pub const PRESENT: u64 = 1 << 0;
+pub const WRITABLE: u64 = 1 << 1;
+pub const USER: u64 = 1 << 2;
+pub const HUGE_PAGE: u64 = 1 << 7;
+pub const NO_EXECUTE: u64 = 1 << 63;
+
+const ADDRESS_MASK: u64 = 0x000f_ffff_ffff_f000;
+const ENTRY_COUNT: usize = 512;
+
+#[derive(Clone, Copy)]
+pub struct Mapping {
+    pub virt: u64,
+    pub phys: u64,
+    pub size: u64,
+    pub page_size: u64,
+    pub flags: u64,
+}
+
+impl Mapping {
+    fn extend(&mut self, next: &Mapping) -> bool {
+        let contiguous = self.virt + self.size == next.virt && self.phys + self.size == next.phys;
+        if contiguous && self.page_size == next.page_size && self.flags == next.flags {
+            self.size += next.size;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+pub fn read_cr3() -> u64 {
+    let cr3: u64;
+    unsafe { asm!("mov {}, cr3", out(reg) cr3, options(nomem, nostack, preserves_flags)) };
+    cr3
+}
+
+// Page tables are reached through their physical address, which relies on the
+// identity mapping set up by the firmware.
+fn table(address: u64) -> &'static [u64; ENTRY_COUNT] {
+    unsafe { &*((address & ADDRESS_MASK) as *const [u64; ENTRY_COUNT]) }
+}
+
+fn canonical(address: u64) -> u64 {
+    (((address << 16) as i64) >> 16) as u64
+}
+
+pub fn walk<F>(f: F)
+where
+    F: FnMut(Mapping),
+{
+    walk_from(read_cr3(), f)
+}
+
+pub fn walk_from<F>(pml4: u64, mut f: F)
+where
+    F: FnMut(Mapping),
+{
+    walk_level(pml4, 4, 0, WRITABLE | USER, &mut f);
+}
+
+// Write and user access must be allowed at every level, while no-execute at
+// any level applies to the whole subtree.
+fn walk_level<F>(table_address: u64, level: u32, base: u64, parent_flags: u64, f: &mut F)
+where
+    F: FnMut(Mapping),
+{
+    let page_size = 1u64 << (12 + 9 * (level - 1));
+    for (index, &entry) in table(table_address).iter().enumerate() {
+        if entry & PRESENT == 0 {
+            continue;
+        }
+        let virt = base + index as u64 * page_size;
+        let flags =
+            (parent_flags & entry & (WRITABLE | USER)) | ((parent_flags | entry) & NO_EXECUTE);
+        if level == 1 || (level <= 3 && entry & HUGE_PAGE != 0) {
+            f(Mapping {
+                virt: canonical(virt),
+                phys: entry & ADDRESS_MASK & !(page_size - 1),
+                size: page_size,
+                page_size,
+                flags,
+            });
+        } else {
+            walk_level(entry, level - 1, virt, flags, f);
+        }
+    }
+}
+
+pub fn print_mappings() {
+    let mut current: Option<Mapping> = None;
+    walk(|mapping| {
+        if let Some(range) = current.as_mut() {
+            if range.extend(&mapping) {
+                return;
+            }
+            print_mapping(range);
+        }
+        current = Some(mapping);
+    });
+    if let Some(range) = current {
+        print_mapping(&range);
+    }
+}
+
+fn print_mapping(mapping: &Mapping) {
+    let page_size = match mapping.page_size {
+        0x1000 => "4K",
+        0x20_0000 => "2M",
+        _ => "1G",
+    };
+    let writable = if mapping.flags & WRITABLE != 0 {
+        'W'
+    } else {
+        '-'
+    };
+    let user = if mapping.flags & USER != 0 { 'U' } else { '-' };
+    let no_execute = if mapping.flags & NO_EXECUTE != 0 {
+        "NX"
+    } else {
+        "--"
+    };
+    println!(
+        "{:016X}-{:016X} -> {:016X} {writable}{user}{no_execute} {page_size}",
+        mapping.virt,
+        mapping.virt + mapping.size - 1,
+        mapping.phys,
+    );
+}
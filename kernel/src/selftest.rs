@@ -0,0 +1,99 @@
+use core::ptr::{read_volatile, write_volatile};
+
+use crate::{
+    device::pci::{init_pci, Pci},
+    graphic::PIXEL_WRITER,
+    println,
+};
+
+pub struct SelfTest {
+    pub name: &'static str,
+    pub run: fn() -> Result<(), &'static str>,
+}
+
+static SELF_TESTS: [SelfTest; 2] = [
+    SelfTest {
+        name: "framebuffer pattern",
+        run: framebuffer_pattern,
+    },
+    SelfTest {
+        name: "pci config space",
+        run: pci_config_space,
+    },
+];
+
+#[cfg(feature = "selftest")]
+crate::initcall!(SELF_TEST_INITCALL, "selftest", Ui, ["pci"], || {
+    if run_self_tests() {
+        Ok(())
+    } else {
+        Err(())
+    }
+});
+
+pub fn run_self_tests() -> bool {
+    let mut failed = 0;
+    for test in SELF_TESTS.iter() {
+        match (test.run)() {
+            Ok(()) => println!("[ PASS ] {}", test.name),
+            Err(reason) => {
+                println!("[ FAIL ] {}: {}", test.name, reason);
+                failed += 1;
+            }
+        }
+    }
+    println!(
+        "self test: {} passed, {} failed",
+        SELF_TESTS.len() - failed,
+        failed
+    );
+    failed == 0
+}
+
+// Walks a row of the framebuffer with the classic stuck-bit patterns and puts
+// the original pixels back afterwards.
+fn framebuffer_pattern() -> Result<(), &'static str> {
+    const PATTERNS: [u8; 4] = [0x00, 0xff, 0xaa, 0x55];
+    let writer = PIXEL_WRITER.get().ok_or("no framebuffer")?;
+    let width = writer.frame_config.resolution().0.min(64);
+
+    let mut result = Ok(());
+    for x in 0..width {
+        let pixel = writer.pixel(x, 0);
+        let saved = [pixel[0], pixel[1], pixel[2], pixel[3]];
+        for pattern in PATTERNS {
+            for byte in pixel.iter_mut() {
+                unsafe { write_volatile(byte, pattern) };
+            }
+            if pixel
+                .iter()
+                .any(|byte| unsafe { read_volatile(byte) } != pattern)
+            {
+                result = Err("read back mismatch");
+            }
+        }
+        pixel.copy_from_slice(&saved);
+    }
+    result
+}
+
+fn pci_config_space() -> Result<(), &'static str> {
+    let pci = init_pci().lock();
+    if pci.device_iter().is_empty() {
+        return Err("no devices found");
+    }
+    for dev in pci.device_iter() {
+        if dev.read_vendor_id() == 0xffff {
+            return Err("device vanished after scan");
+        }
+        let class_code = Pci::read_class_code(dev.bus, dev.dev, dev.func);
+        if !class_code.is_class(
+            dev.class_code.base,
+            dev.class_code.sub,
+            dev.class_code.interface,
+        ) {
+            return Err("class code is not stable");
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,98 @@
+use core::fmt;
+
+use crate::sync::Mutex;
+
+pub const SYSNAME: &str = "RedOS";
+pub const RELEASE: &str = env!("CARGO_PKG_VERSION");
+pub const MACHINE: &str = "x86_64";
+pub const BUILD_PROFILE: &str = if cfg!(debug_assertions) {
+    "debug"
+} else {
+    "release"
+};
+
+static HOSTNAME: Mutex<Hostname> = Mutex::new(Hostname::new());
+
+#[derive(Clone, Copy)]
+pub struct Hostname {
+    name: [u8; Hostname::MAX_LEN],
+    len: usize,
+}
+
+pub struct Uname {
+    pub sysname: &'static str,
+    pub nodename: Hostname,
+    pub release: &'static str,
+    pub machine: &'static str,
+}
+
+impl Hostname {
+    pub const MAX_LEN: usize = 63;
+
+    const fn new() -> Self {
+        let mut name = [0u8; Hostname::MAX_LEN];
+        let default = b"redos";
+        let mut i = 0;
+        while i < default.len() {
+            name[i] = default[i];
+            i += 1;
+        }
+        Self {
+            name,
+            len: default.len(),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        core::str::from_utf8(&self.name[..self.len]).unwrap_or("")
+    }
+}
+
+impl fmt::Display for Hostname {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Display for Uname {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} ({}) {}",
+            self.sysname, self.nodename, self.release, BUILD_PROFILE, self.machine
+        )
+    }
+}
+
+pub fn hostname() -> Hostname {
+    *HOSTNAME.lock()
+}
+
+// Follows the RFC 1123 label rules: letters, digits and inner hyphens.
+pub fn set_hostname(name: &str) -> Result<(), ()> {
+    let bytes = name.as_bytes();
+    if bytes.is_empty()
+        || bytes.len() > Hostname::MAX_LEN
+        || bytes[0] == b'-'
+        || bytes[bytes.len() - 1] == b'-'
+        || !bytes
+            .iter()
+            .all(|&c| c.is_ascii_alphanumeric() || c == b'-')
+    {
+        return Err(());
+    }
+
+    let mut hostname = HOSTNAME.lock();
+    hostname.name[..bytes.len()].copy_from_slice(bytes);
+    hostname.len = bytes.len();
+    Ok(())
+}
+
+pub fn uname() -> Uname {
+    Uname {
+        sysname: SYSNAME,
+        nodename: hostname(),
+        release: RELEASE,
+        machine: MACHINE,
+    }
+}